@@ -0,0 +1,34 @@
+//! Compare the throughput of [blockchain_pow_julia::do_work_parallel] for a few chunk sizes.
+//!
+//! `cargo bench --bench chunk_size`
+
+use std::{num::NonZeroUsize, time::Instant};
+
+fn main() {
+    let c = num::Complex { re: 0.5, im: 0.5 };
+    let target_iterations = 10;
+    let num_threads = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    let rounds = 200;
+    for chunk_size in [
+        1,
+        100,
+        blockchain_pow_julia::default_chunk_size(target_iterations).get(),
+        100_000,
+    ] {
+        let start = Instant::now();
+        for _ in 0..rounds {
+            blockchain_pow_julia::do_work_parallel(
+                c,
+                0.0,
+                0.5,
+                target_iterations,
+                num_threads,
+                NonZeroUsize::new(chunk_size).unwrap(),
+            );
+        }
+        println!(
+            "chunk_size={chunk_size:>6}: {:?} per solution",
+            start.elapsed() / rounds
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! Compare how long a node takes to validate a block full of transfers, for a few
+//! [blockchain_pow_julia::ValidatorNode::with_validation_threads].
+//!
+//! `cargo bench --bench validate_block --features testing`
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use blockchain_pow_julia::{
+    testing::{mine, Wallet},
+    ValidatorNode,
+};
+
+fn main() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let genesis = mine(
+        None,
+        vec![alice.new_user(), bob.new_user(), alice.mint(u64::MAX)],
+    );
+    let transfers = mine(
+        Some(genesis.inner.id),
+        (1..=1000)
+            .map(|amount| alice.transfer(&bob, amount))
+            .collect(),
+    );
+    let available = std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+    for num_threads in [1, 2, available.get()] {
+        let mut node =
+            ValidatorNode::new().with_validation_threads(NonZeroUsize::new(num_threads).unwrap());
+        node.ingest_block(genesis.clone()).unwrap();
+        let start = Instant::now();
+        node.ingest_block(transfers.clone()).unwrap();
+        println!(
+            "num_threads={num_threads:>3}: {:?} for {} transfers",
+            start.elapsed(),
+            transfers.inner.events.len()
+        );
+    }
+}
@@ -1,6 +1,19 @@
+//! Search for proof of work solutions.
+//!
+//! While running, type `pause` or `resume` (followed by enter) to pause or resume all the
+//! workers, e.g to free up the CPU for a while. Workers start running.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
 use clap::Parser as _;
 use color_eyre::eyre::Context as _;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, clap::Parser)]
 struct Args {
@@ -10,9 +23,9 @@ struct Args {
     julia_c_real: f64,
     #[arg(short = 'i', long, default_value_t = 0.5)]
     julia_c_imag: f64,
-    #[arg(short = 'l', long, default_value_t = 0.0)]
+    #[arg(short = 'l', long, default_value_t = blockchain_pow_julia::DEFAULT_RE_MIN)]
     real_lower_bound: f64,
-    #[arg(short = 'u', long, default_value_t = 0.5)]
+    #[arg(short = 'u', long, default_value_t = blockchain_pow_julia::DEFAULT_RE_MAX)]
     real_upper_bound: f64,
     #[arg(short = 't', long, default_value_t = 10)]
     target_iterations: u16,
@@ -36,19 +49,48 @@ fn main() -> color_eyre::Result<()> {
     let num_threads = match (args.num_threads, std::thread::available_parallelism()) {
         (Some(user), _) => user,
         (None, Ok(default)) => default.get(),
-        (_, Err(err)) => {
-            return Err(color_eyre::Report::from(err))
-                .context("couldn't get a default for num threads")
+        (None, Err(error)) => {
+            warn!(%error, "couldn't get a default for num threads, using 1");
+            1
         }
     };
     info!("spawning {num_threads} threads");
 
     let (sender, receiver) = std::sync::mpsc::channel();
+    let paused = Arc::new(AtomicBool::new(false));
+
+    {
+        let paused = paused.clone();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                match line.as_deref().map(str::trim) {
+                    Ok("pause") => {
+                        info!("pausing");
+                        paused.store(true, Ordering::Relaxed)
+                    }
+                    Ok("resume") => {
+                        info!("resuming");
+                        paused.store(false, Ordering::Relaxed)
+                    }
+                    Ok(other) => warn!(command = other, "expected `pause` or `resume`"),
+                    Err(error) => {
+                        warn!(%error, "couldn't read commands from stdin");
+                        break;
+                    }
+                }
+            }
+        });
+    }
 
     for thread_num in 0..num_threads {
-        let sender = sender.clone();
+        let (sender, paused) = (sender.clone(), paused.clone());
         std::thread::spawn(move || loop {
-            let found = blockchain_pow_julia::do_work(
+            // pausing cancels the current search, which can be resumed with no loss
+            // since every attempt is independent
+            while paused.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let Some(found) = blockchain_pow_julia::do_work_cancellable(
                 num::Complex {
                     re: args.julia_c_real,
                     im: args.julia_c_imag,
@@ -56,7 +98,11 @@ fn main() -> color_eyre::Result<()> {
                 args.real_lower_bound,
                 args.real_upper_bound,
                 args.target_iterations,
-            );
+                blockchain_pow_julia::default_chunk_size(args.target_iterations),
+                &paused,
+            ) else {
+                continue;
+            };
             info!(%thread_num, %found);
             if !args.keep_going {
                 sender.send(found).unwrap();
@@ -1,9 +1,18 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
 
 use itertools::Itertools as _;
 
 use crate::LedgerEvent;
 
+/// The blocks from a root block down to some block, in order.
+pub type Chain<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT> =
+    Vec<Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>>;
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT> {
     pub parent: Option<BlockIdT>,
@@ -11,11 +20,75 @@ pub struct Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT> {
     pub events: Vec<LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>>,
 }
 
+impl<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>
+    Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>
+{
+    /// Whether this block has no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The benefactor and signature of each transfer in this block, in order.
+    pub fn signatures(&self) -> Vec<(&UserIdT, &SignatureT)> {
+        self.events
+            .iter()
+            .filter_map(LedgerEvent::as_transfer)
+            .map(|(benefactor, _, _, _, signature)| (benefactor, signature))
+            .collect()
+    }
+}
+
+impl<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>
+    Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>
+where
+    UserIdT: Hash + Eq + Clone,
+{
+    /// Every account that is created, minted to, or transferred to or from in this block.
+    pub fn affected_accounts(&self) -> HashSet<UserIdT> {
+        self.events
+            .iter()
+            .flat_map(|event| match event {
+                LedgerEvent::NewUser { identifier, .. } => vec![identifier],
+                LedgerEvent::Mint { beneficiary, .. } => vec![beneficiary],
+                LedgerEvent::Transfer {
+                    benefactor,
+                    beneficiary,
+                    ..
+                } => vec![benefactor, beneficiary],
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// How many leading blocks `a` and `b` have in common, by id.
+///
+/// For two chains which start at the same root, everything after this is a fork.
+pub fn common_prefix<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>(
+    a: &[Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>],
+    b: &[Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>],
+) -> usize
+where
+    BlockIdT: PartialEq,
+{
+    a.iter().zip(b).take_while(|(a, b)| a.id == b.id).count()
+}
+
 /// Keeps track of blocks.
 /// Does not perform any verification.
+///
+/// The winning chain is the root-to-leaf chain with, in order of precedence:
+/// 1. the most total work,
+/// 2. the most blocks,
+/// 3. the (lexicographically) smallest tip id.
+///
+/// This is consensus-critical: every node must apply the same rules to agree on the winning chain.
 pub struct BlockGraph<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT> {
     block_ids_to_blocks:
         HashMap<BlockIdT, Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>>,
+    block_ids_to_work: HashMap<BlockIdT, u64>,
+    /// See [Self::first_seen].
+    block_ids_to_first_seen: HashMap<BlockIdT, u64>,
     block_id_graph: petgraph::graphmap::DiGraphMap<BlockIdT, ()>,
     winning_chain: Vec<Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>>,
 }
@@ -28,6 +101,8 @@ where
     fn default() -> Self {
         Self {
             block_ids_to_blocks: Default::default(),
+            block_ids_to_work: Default::default(),
+            block_ids_to_first_seen: Default::default(),
             block_id_graph: petgraph::graphmap::DiGraphMap::new(),
             winning_chain: Default::default(),
         }
@@ -43,9 +118,19 @@ where
     PublicKeyT: PartialEq + Clone,
     SignatureT: PartialEq + Clone,
 {
+    /// Add a block worth one unit of work.
     pub fn add_block(
         &mut self,
         block: Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>,
+    ) -> Result<AddBlockOk, AddBlockError> {
+        self.add_block_with_work(block, 1)
+    }
+
+    /// Add a block, which contributes `work` to the total work of any chain it is on.
+    pub fn add_block_with_work(
+        &mut self,
+        block: Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>,
+        work: u64,
     ) -> Result<AddBlockOk, AddBlockError> {
         use std::collections::hash_map::Entry;
         match self.block_ids_to_blocks.entry(block.id) {
@@ -53,9 +138,18 @@ where
             Entry::Occupied(_) => Err(AddBlockError::WouldClobber),
             Entry::Vacant(vacancy) => {
                 vacancy.insert(block.clone());
+                self.block_ids_to_work.insert(block.id, work);
+                let first_seen = self.block_ids_to_first_seen.len() as u64;
+                self.block_ids_to_first_seen.insert(block.id, first_seen);
                 match (block.parent, self.winning_chain.last()) {
-                    (Some(parent), Some(tail)) if parent == tail.id => {
-                        // fast path - we don't need to recalculate the winning chain
+                    (Some(parent), Some(tail))
+                        if parent == tail.id
+                            && self.block_id_graph.neighbors(block.id).next().is_none() =>
+                    {
+                        // fast path - we don't need to recalculate the winning chain,
+                        // because work is never negative.
+                        // (If children arrived before this block, the chain is longer than
+                        // just this block)
                         self.block_id_graph.add_edge(parent, block.id, ());
                         self.winning_chain.push(block);
                         Ok(AddBlockOk::CanAddNewEventsToLedger)
@@ -75,45 +169,164 @@ where
         }
     }
 
+    pub fn get(
+        &self,
+        id: &BlockIdT,
+    ) -> Option<&Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
+        self.block_ids_to_blocks.get(id)
+    }
+
+    /// The blocks from a root block down to `id` (inclusive).
+    ///
+    /// Returns [None] if `id` isn't in the graph, or isn't (yet) connected to a root block.
+    pub fn chain_to(
+        &self,
+        id: &BlockIdT,
+    ) -> Option<Chain<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
+        let mut chain = vec![self.block_ids_to_blocks.get(id)?.clone()];
+        while let Some(parent) = chain.last().and_then(|it| it.parent) {
+            chain.push(self.block_ids_to_blocks.get(&parent)?.clone());
+        }
+        chain.reverse();
+        Some(chain)
+    }
+
+    /// When this block was first added to the graph, relative to the other blocks:
+    /// the first block added is `0`, the second `1`, and so on.
+    ///
+    /// Adding the same block again doesn't change this.
+    pub fn first_seen(&self, id: &BlockIdT) -> Option<u64> {
+        self.block_ids_to_first_seen.get(id).copied()
+    }
+
+    /// Every block (on any fork) whose distance from its root block is `height`, sorted by id.
+    /// Root blocks have height 0.
+    pub fn blocks_at_height(
+        &self,
+        height: usize,
+    ) -> Vec<&Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
+        self.block_ids_to_blocks
+            .values()
+            .filter(|it| self.height(&it.id) == Some(height))
+            .sorted_by_key(|it| it.id)
+            .collect()
+    }
+
+    /// Returns [None] if `id` isn't in the graph, or isn't (yet) connected to a root block.
+    fn height(&self, id: &BlockIdT) -> Option<usize> {
+        let mut block = self.block_ids_to_blocks.get(id)?;
+        let mut height = 0;
+        while let Some(parent) = block.parent {
+            block = self.block_ids_to_blocks.get(&parent)?;
+            height += 1;
+        }
+        Some(height)
+    }
+
+    /// How many blocks are in the graph, on any fork.
+    pub fn block_count(&self) -> usize {
+        self.block_ids_to_blocks.len()
+    }
+
+    /// How many blocks are in the graph, but not on the winning chain.
+    pub fn stale_count(&self) -> usize {
+        self.block_count() - self.winning_chain.len()
+    }
+
+    /// The fraction of blocks that aren't on the winning chain, from `0.0` to `1.0`.
+    /// An empty graph has a stale rate of `0.0`.
+    pub fn stale_rate(&self) -> f64 {
+        match self.block_count() {
+            0 => 0.0,
+            total => self.stale_count() as f64 / total as f64,
+        }
+    }
+
+    /// The ids of blocks only in `self`, and of blocks only in `other`, each in order.
+    ///
+    /// Blocks are compared by id alone, e.g to see where two nodes disagree.
+    pub fn diff(&self, other: &Self) -> (Vec<BlockIdT>, Vec<BlockIdT>) {
+        let only_in = |left: &Self, right: &Self| {
+            left.block_ids_to_blocks
+                .keys()
+                .filter(|it| !right.block_ids_to_blocks.contains_key(it))
+                .copied()
+                .sorted()
+                .collect()
+        };
+        (only_in(self, other), only_in(other, self))
+    }
+
     pub fn winning_chain(&self) -> Vec<Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
         self.winning_chain.clone()
     }
 
+    /// The blocks of [Self::winning_chain], from the root, without cloning them.
+    pub fn iter_winning_chain(
+        &self,
+    ) -> std::slice::Iter<'_, Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
+        self.winning_chain.iter()
+    }
+
+    /// The total work of the winning chain, in the units passed to [Self::add_block_with_work].
+    ///
+    /// This is what fork choice compares first, see [BlockGraph].
+    pub fn total_work(&self) -> u64 {
+        self.winning_chain.iter().map(|it| self.work(&it.id)).sum()
+    }
+
+    /// A [ChainRow] for each block on the winning chain, from the root.
+    pub fn chain_summary(&self) -> Vec<ChainRow>
+    where
+        BlockIdT: fmt::LowerHex,
+    {
+        self.winning_chain
+            .iter()
+            .enumerate()
+            .map(|(height, block)| ChainRow {
+                height,
+                id: abbreviate(&block.id),
+                parent: block.parent.as_ref().map(abbreviate),
+                event_count: block.events.len(),
+            })
+            .collect()
+    }
+
     fn calculate_winning_chain(
         &self,
     ) -> Vec<Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>> {
-        let mut winner = Vec::new();
-        for root in self.root_blocks() {
-            for leaf in self.leaf_blocks() {
-                if root == leaf && winner.is_empty() {
-                    winner.push(*root);
-                    continue;
-                }
-
-                match petgraph::algo::all_simple_paths::<Vec<_>, _>(
-                    &self.block_id_graph,
-                    *root,
-                    *leaf,
-                    0,
-                    None,
-                )
-                .at_most_one()
-                {
-                    Ok(Some(candidate)) if candidate.len() > winner.len() => winner = candidate,
-                    Ok(Some(_)) | Ok(None) => (),
-                    Err(_) => unreachable!("each block is unique, and has at most one parent, so there cannot be multiple paths between two blocks"),
-                }
+        // Each block has at most one parent, so each leaf we can reach from a root is the
+        // tip of exactly one chain.
+        // Walk down from the roots, totting up each chain as we go.
+        let mut best = None;
+        let mut stack = self
+            .root_blocks()
+            .into_iter()
+            .map(|root| (*root, self.work(root), 1))
+            .collect::<Vec<_>>();
+        while let Some((id, work, len)) = stack.pop() {
+            let mut children = self.block_id_graph.neighbors(id).peekable();
+            if children.peek().is_none() {
+                // See [BlockGraph] for the rules
+                best = best.max(Some((work, len, Reverse(id))));
+            }
+            for child in children {
+                stack.push((child, work + self.work(&child), len + 1));
             }
         }
-        winner
-            .into_iter()
-            .map(|block_id| {
-                self.block_ids_to_blocks
-                    .get(&block_id)
-                    .expect("BlockGraph.blocks and BlockGraph.graph are out of sync")
-                    .clone()
-            })
-            .collect()
+        match best {
+            Some((_, _, Reverse(tip))) => {
+                self.chain_to(&tip).expect("we reached the tip from a root")
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn work(&self, id: &BlockIdT) -> u64 {
+        *self
+            .block_ids_to_work
+            .get(id)
+            .expect("BlockGraph.blocks and BlockGraph.work are out of sync")
     }
 
     fn root_blocks(&self) -> Vec<&BlockIdT> {
@@ -123,14 +336,6 @@ where
                 Some(_) => None,
                 None => Some(&it.id),
             })
-            .sorted() // deterministic winning chain
-            .collect()
-    }
-    fn leaf_blocks(&self) -> Vec<&BlockIdT> {
-        self.block_ids_to_blocks
-            .keys()
-            .filter(|it| self.block_id_graph.neighbors(**it).count() == 0)
-            .sorted() // deterministic winning chain
             .collect()
     }
 }
@@ -148,6 +353,42 @@ pub enum AddBlockError {
     WouldClobber,
 }
 
+/// A block on the winning chain, see [BlockGraph::chain_summary].
+///
+/// Displays as a row of a table, under [ChainRow::HEADER].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainRow {
+    pub height: usize,
+    /// The first [ChainRow::ID_LEN] hex digits of the block's id.
+    pub id: String,
+    pub parent: Option<String>,
+    pub event_count: usize,
+}
+
+impl ChainRow {
+    pub const ID_LEN: usize = 8;
+    pub const HEADER: &'static str = "height  id        parent    events";
+}
+
+impl fmt::Display for ChainRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>6}  {:<8}  {:<8}  {:>6}",
+            self.height,
+            self.id,
+            self.parent.as_deref().unwrap_or("-"),
+            self.event_count
+        )
+    }
+}
+
+fn abbreviate(id: &impl fmt::LowerHex) -> String {
+    let mut hex = format!("{id:x}");
+    hex.truncate(ChainRow::ID_LEN);
+    hex
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,14 +405,32 @@ mod tests {
             .unwrap();
     }
 
+    fn add_block_with_work(
+        graph: &mut TestBlockGraph,
+        parent: impl Into<Option<char>>,
+        id: char,
+        work: u64,
+    ) {
+        graph
+            .add_block_with_work(
+                Block {
+                    parent: parent.into(),
+                    id,
+                    events: vec![],
+                },
+                work,
+            )
+            .unwrap();
+    }
+
     fn assert_winning_chain(graph: &TestBlockGraph, chain: impl IntoIterator<Item = char>) {
         let expected = chain.into_iter().collect::<Vec<_>>();
         let actual = graph
-            .winning_chain()
-            .into_iter()
+            .iter_winning_chain()
             .map(|it| it.id)
             .collect::<Vec<_>>();
-        assert_eq!(expected, actual)
+        assert_eq!(expected, actual);
+        assert!(graph.winning_chain().iter().eq(graph.iter_winning_chain()));
     }
 
     #[test]
@@ -221,4 +480,364 @@ mod tests {
         add_block(graph, 'a', 'c');
         assert_winning_chain(graph, ['a', 'c', 'd']);
     }
+
+    #[test]
+    fn out_of_order_child_of_tip() {
+        let graph = &mut TestBlockGraph::default();
+        add_block(graph, None, 'a');
+        add_block(graph, 'b', 'c');
+        add_block(graph, 'a', 'b');
+        assert_winning_chain(graph, ['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn most_work_wins() {
+        let graph = &mut TestBlockGraph::default();
+        add_block_with_work(graph, None, 'a', 1);
+        add_block_with_work(graph, 'a', 'b', 1);
+        add_block_with_work(graph, 'b', 'c', 1);
+        add_block_with_work(graph, 'a', 'd', 5);
+        assert_winning_chain(graph, ['a', 'd']);
+
+        // also applies across roots
+        let graph = &mut TestBlockGraph::default();
+        add_block_with_work(graph, None, 'a', 1);
+        add_block_with_work(graph, 'a', 'b', 1);
+        add_block_with_work(graph, None, 'z', 3);
+        assert_winning_chain(graph, ['z']);
+    }
+
+    #[test]
+    fn equal_work_most_blocks_wins() {
+        let graph = &mut TestBlockGraph::default();
+        add_block_with_work(graph, None, 'a', 1);
+        add_block_with_work(graph, 'a', 'b', 2);
+        add_block_with_work(graph, 'a', 'c', 1);
+        add_block_with_work(graph, 'c', 'd', 1);
+        assert_winning_chain(graph, ['a', 'c', 'd']);
+    }
+
+    #[test]
+    fn equal_work_and_blocks_smallest_tip_wins() {
+        let graph = &mut TestBlockGraph::default();
+        add_block_with_work(graph, None, 'a', 1);
+        add_block_with_work(graph, 'a', 'c', 1);
+        add_block_with_work(graph, 'a', 'b', 1);
+        assert_winning_chain(graph, ['a', 'b']);
+
+        let graph = &mut TestBlockGraph::default();
+        add_block_with_work(graph, None, 'a', 1);
+        add_block_with_work(graph, 'a', 'b', 1);
+        add_block_with_work(graph, 'a', 'c', 1);
+        assert_winning_chain(graph, ['a', 'b']);
+    }
+
+    #[test]
+    fn blocks_at_height() {
+        let graph = &mut TestBlockGraph::default();
+        add_block(graph, None, 'a');
+        add_block(graph, 'a', 'b');
+        add_block(graph, 'b', 'd');
+        add_block(graph, 'a', 'c');
+        add_block(graph, 'x', 'y'); // not connected to a root
+        let ids_at = |height| {
+            graph
+                .blocks_at_height(height)
+                .into_iter()
+                .map(|it| it.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids_at(0), ['a']);
+        assert_eq!(ids_at(1), ['b', 'c']);
+        assert_eq!(ids_at(2), ['d']);
+        assert_eq!(ids_at(3), [] as [char; 0]);
+    }
+
+    #[test]
+    fn stale_rate() {
+        let graph = &mut TestBlockGraph::default();
+        assert_eq!(graph.stale_rate(), 0.0);
+        add_block(graph, None, 'a');
+        add_block(graph, 'a', 'b');
+        add_block(graph, 'b', 'c');
+        assert_eq!(graph.stale_rate(), 0.0);
+        add_block(graph, 'a', 'd');
+        add_block(graph, None, 'e');
+        assert_winning_chain(graph, ['a', 'b', 'c']);
+        assert_eq!(graph.block_count(), 5);
+        assert_eq!(graph.stale_count(), 2);
+        assert_eq!(graph.stale_rate(), 0.4);
+    }
+
+    #[test]
+    fn first_seen() {
+        let graph = &mut TestBlockGraph::default();
+        add_block(graph, None, 'b');
+        add_block(graph, 'c', 'd');
+        add_block(graph, None, 'a');
+        add_block(graph, None, 'b');
+        assert_eq!(graph.first_seen(&'b'), Some(0));
+        assert_eq!(graph.first_seen(&'d'), Some(1));
+        assert_eq!(graph.first_seen(&'a'), Some(2));
+        assert_eq!(graph.first_seen(&'c'), None);
+    }
+
+    #[test]
+    fn common_prefix() {
+        let chain = |ids: &str| {
+            ids.chars()
+                .scan(None, |parent, id| {
+                    let block = Block {
+                        parent: *parent,
+                        id,
+                        events: vec![],
+                    };
+                    *parent = Some(id);
+                    Some(block)
+                })
+                .collect::<Vec<Block<char, (), (), (), ()>>>()
+        };
+        assert_eq!(super::common_prefix(&chain("abc"), &chain("abc")), 3);
+        assert_eq!(super::common_prefix(&chain("abcd"), &chain("abxyz")), 2);
+        assert_eq!(super::common_prefix(&chain("ab"), &chain("abcd")), 2);
+        assert_eq!(super::common_prefix(&chain("abc"), &chain("xyz")), 0);
+        assert_eq!(super::common_prefix(&chain(""), &chain("abc")), 0);
+    }
+
+    #[test]
+    fn signatures() {
+        let block = Block::<char, char, u64, (), &str> {
+            parent: None,
+            id: 'a',
+            events: vec![
+                LedgerEvent::NewUser {
+                    identifier: 'x',
+                    public_key: (),
+                },
+                LedgerEvent::Transfer {
+                    benefactor: 'x',
+                    beneficiary: 'y',
+                    amount: 1,
+                    nonce: 0,
+                    benefactor_signature: "first",
+                },
+                LedgerEvent::Mint {
+                    beneficiary: 'y',
+                    amount: 1,
+                    authority_signature: None,
+                },
+                LedgerEvent::Transfer {
+                    benefactor: 'y',
+                    beneficiary: 'x',
+                    amount: 1,
+                    nonce: 0,
+                    benefactor_signature: "second",
+                },
+            ],
+        };
+        assert_eq!(block.signatures(), [(&'x', &"first"), (&'y', &"second")]);
+    }
+
+    #[test]
+    fn affected_accounts() {
+        let block = Block::<char, char, u64, (), ()> {
+            parent: None,
+            id: 'a',
+            events: vec![
+                LedgerEvent::NewUser {
+                    identifier: 'x',
+                    public_key: (),
+                },
+                LedgerEvent::Mint {
+                    beneficiary: 'y',
+                    amount: 1,
+                    authority_signature: None,
+                },
+                LedgerEvent::Transfer {
+                    benefactor: 'y',
+                    beneficiary: 'z',
+                    amount: 1,
+                    nonce: 0,
+                    benefactor_signature: (),
+                },
+            ],
+        };
+        assert_eq!(block.affected_accounts(), HashSet::from(['x', 'y', 'z']));
+    }
+
+    /// A random forest of `size` blocks, as `(parent, work)` for each block id.
+    ///
+    /// Parents always have a smaller id than their children, so every block is connected
+    /// to a root once everything has been added.
+    fn random_forest(rng: &mut impl rand::Rng, size: u8, max_work: u64) -> Vec<(Option<u8>, u64)> {
+        (0..size)
+            .map(|id| {
+                let parent = match id == 0 || rng.gen_bool(0.2) {
+                    true => None,
+                    false => Some(rng.gen_range(0..id)),
+                };
+                (parent, rng.gen_range(1..=max_work))
+            })
+            .collect()
+    }
+
+    /// The winning chain after adding the forest to a graph in (a random) order.
+    fn winning_chain_of(
+        forest: &[(Option<u8>, u64)],
+        order: &[u8],
+    ) -> Vec<Block<u8, (), (), (), ()>> {
+        let mut graph = BlockGraph::<u8, (), (), (), ()>::default();
+        for &id in order {
+            let (parent, work) = forest[usize::from(id)];
+            graph
+                .add_block_with_work(
+                    Block {
+                        parent,
+                        id,
+                        events: vec![],
+                    },
+                    work,
+                )
+                .unwrap();
+        }
+        graph.winning_chain()
+    }
+
+    /// Check that fork choice follows the rules in [BlockGraph], however the blocks arrive.
+    ///
+    /// Cases are generated from their seed, and small forests come first, so the first
+    /// failure is usually a small one.
+    #[test]
+    fn fork_choice_invariants() {
+        use rand::{seq::SliceRandom as _, SeedableRng as _};
+        for seed in 0..1000 {
+            let rng = &mut rand::rngs::StdRng::seed_from_u64(seed);
+            let size = 1 + (seed / 50) as u8;
+            let max_work = match seed % 2 {
+                0 => 1, // so the longest chain should win
+                _ => 3,
+            };
+            let forest = random_forest(rng, size, max_work);
+            let mut order = (0..size).collect::<Vec<_>>();
+            order.shuffle(rng);
+            let winning = winning_chain_of(&forest, &order);
+            let context = format!("seed {seed}, forest {forest:?}, order {order:?}");
+
+            // a root-to-leaf path, following parent links
+            assert_eq!(winning[0].parent, None, "{context}");
+            for (parent, child) in winning.iter().tuple_windows() {
+                assert_eq!(child.parent, Some(parent.id), "{context}");
+            }
+            let tip = winning.last().unwrap().id;
+            assert!(
+                forest.iter().all(|(parent, _)| *parent != Some(tip)),
+                "{context}"
+            );
+
+            // maximal under the rules, compared against every chain
+            let key = |tip: u8| {
+                let mut chain = vec![tip];
+                while let Some(parent) = forest[usize::from(*chain.last().unwrap())].0 {
+                    chain.push(parent);
+                }
+                let work = chain
+                    .iter()
+                    .map(|it| forest[usize::from(*it)].1)
+                    .sum::<u64>();
+                (work, chain.len(), Reverse(tip))
+            };
+            for other in 0..size {
+                assert!(key(tip) >= key(other), "{context}, beaten by {other}");
+                if max_work == 1 {
+                    assert!(winning.len() >= key(other).1, "{context}");
+                }
+            }
+
+            // independent of insertion order
+            order.shuffle(rng);
+            assert_eq!(
+                winning,
+                winning_chain_of(&forest, &order),
+                "{context}, reordered to {order:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let mut ours = TestBlockGraph::default();
+        let mut theirs = TestBlockGraph::default();
+        for graph in [&mut ours, &mut theirs] {
+            add_block(graph, None, 'a');
+            add_block(graph, 'a', 'b');
+            add_block(graph, 'b', 'c');
+        }
+        assert_eq!(ours.diff(&theirs), (vec![], vec![]));
+
+        add_block(&mut ours, 'c', 'd');
+        add_block(&mut ours, 'd', 'e');
+        add_block(&mut ours, 'b', 'x');
+        add_block(&mut theirs, 'c', 'y');
+        add_block(&mut theirs, 'y', 'z');
+        assert_eq!(ours.diff(&theirs), (vec!['d', 'e', 'x'], vec!['y', 'z']));
+        assert_eq!(theirs.diff(&ours), (vec!['y', 'z'], vec!['d', 'e', 'x']));
+        assert_eq!(
+            TestBlockGraph::default().diff(&theirs),
+            (vec![], vec!['a', 'b', 'c', 'y', 'z'])
+        );
+    }
+
+    #[test]
+    fn total_work() {
+        let graph = &mut TestBlockGraph::default();
+        assert_eq!(graph.total_work(), 0);
+        add_block_with_work(graph, None, 'a', 2);
+        assert_eq!(graph.total_work(), 2);
+        add_block_with_work(graph, 'a', 'b', 3);
+        assert_eq!(graph.total_work(), 5);
+        // a stale block doesn't count
+        add_block_with_work(graph, 'a', 'c', 1);
+        assert_eq!(graph.total_work(), 5);
+        add_block_with_work(graph, 'c', 'd', 3);
+        assert_eq!(graph.total_work(), 6);
+        assert_winning_chain(graph, ['a', 'c', 'd']);
+    }
+
+    #[test]
+    fn chain_summary() {
+        let mut graph = BlockGraph::<u32, (), (), (), ()>::default();
+        for (parent, id, events) in [
+            (None, 0xa, 0),
+            (Some(0xa), 0xb, 2),
+            (Some(0xa), 0xc, 0),
+            (Some(0xc), 0x1234_5678_u32, 1),
+        ] {
+            graph
+                .add_block(Block {
+                    parent,
+                    id,
+                    events: vec![
+                        LedgerEvent::Mint {
+                            beneficiary: (),
+                            amount: (),
+                            authority_signature: None,
+                        };
+                        events
+                    ],
+                })
+                .unwrap();
+        }
+        let summary = graph.chain_summary();
+        assert_eq!(summary.len(), graph.winning_chain().len());
+        assert!(summary.iter().map(|it| it.height).eq(0..summary.len()));
+        assert_eq!(
+            summary.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "     0  a         -              0",
+                "     1  c         a              0",
+                "     2  12345678  c              1",
+            ]
+        );
+        assert_eq!(ChainRow::HEADER.len(), summary[0].to_string().len());
+    }
 }
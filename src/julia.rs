@@ -0,0 +1,77 @@
+//! Checking proofs of work.
+//!
+//! Nothing here allocates or needs std, so it's all that's left without the `std` feature,
+//! for verifying work on devices which couldn't do it.
+
+/// The real bounds that the network uses for the set.
+pub const DEFAULT_RE_MIN: f64 = 0.0;
+pub const DEFAULT_RE_MAX: f64 = 0.5;
+
+pub(crate) fn iterate_julia(
+    c: num_complex::Complex<f64>,
+    z: num_complex::Complex<f64>,
+) -> num_complex::Complex<f64> {
+    z.powu(2) + c
+}
+
+// how can we deal with floating point errors?
+pub fn check_work(
+    c: num_complex::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    candidate: num_complex::Complex<f64>,
+    target_iterations: u16,
+) -> Result<num_complex::Complex<f64>, DoWorkError> {
+    let mut current = candidate;
+    for iteration in 0..=target_iterations {
+        current = iterate_julia(c, current);
+        if current.re < re_min || current.re > re_max {
+            match iteration == target_iterations {
+                true => return Ok(candidate),
+                false => return Err(DoWorkError::LeftSetTooEarly),
+            }
+        }
+    }
+    Err(DoWorkError::LeftSetTooLateOrNotAtAll)
+}
+
+// thiserror needs std
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum DoWorkError {
+    #[cfg_attr(
+        feature = "std",
+        error("candidate left the set before the target number of iterations")
+    )]
+    LeftSetTooEarly,
+    #[cfg_attr(
+        feature = "std",
+        error("candidate didn't leave the set at the target number of iterations")
+    )]
+    LeftSetTooLateOrNotAtAll,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_work() {
+        let c = num_complex::Complex { re: 0.5, im: 0.5 };
+        let candidate = num_complex::Complex { re: 0.25, im: 0.0 };
+        // 0.25 -> 0.5625 + 0.5i, which is already outside the bounds
+        assert_eq!(
+            super::check_work(c, DEFAULT_RE_MIN, DEFAULT_RE_MAX, candidate, 0),
+            Ok(candidate)
+        );
+        assert_eq!(
+            super::check_work(c, DEFAULT_RE_MIN, DEFAULT_RE_MAX, candidate, 1),
+            Err(DoWorkError::LeftSetTooEarly)
+        );
+        let fixed_point = num_complex::Complex { re: 0.0, im: 0.0 };
+        assert_eq!(
+            super::check_work(fixed_point, -1.0, 1.0, fixed_point, 10),
+            Err(DoWorkError::LeftSetTooLateOrNotAtAll)
+        );
+    }
+}
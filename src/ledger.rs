@@ -1,5 +1,15 @@
-use std::{collections::HashMap, hash::Hash};
-use tap::Tap as _;
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::Block;
 
 /// A list of _valid_ events.
 ///
@@ -7,13 +17,197 @@ use tap::Tap as _;
 #[derive(Debug, Clone)]
 pub struct Ledger<UserIdT, AmountT, PublicKeyT, SignatureT> {
     events: Vec<LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>>,
-    // TODO: cache state of the world, and recompute per event
+    /// The state of the world after [Self::events], updated as events are added and undone.
+    ///
+    /// Shared with [LedgerSnapshot]s, and copied on write.
+    users: Arc<HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>>,
+    /// See [Self::with_verifier].
+    verifier: Arc<dyn TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT>>,
+    /// See [Self::with_strict_signatures].
+    strict_signatures: bool,
+    /// See [Self::with_mint_authorities].
+    mint_authorities: Vec<PublicKeyT>,
+    /// See [Self::with_block_reward].
+    block_reward: Option<AmountT>,
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct UserSummary<AmountT, PublicKeyT> {
     balance: AmountT,
     public_key: PublicKeyT,
+    /// How many transfers this user has sent.
+    transfers_sent: u64,
+    /// How many signed mints this user has received.
+    signed_mints_received: u64,
+}
+
+impl<UserIdT, AmountT, PublicKeyT, SignatureT> Default
+    for Ledger<UserIdT, AmountT, PublicKeyT, SignatureT>
+{
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            users: Arc::default(),
+            verifier: Arc::new(AlwaysAccept),
+            strict_signatures: false,
+            mint_authorities: Vec::new(),
+            block_reward: None,
+        }
+    }
+}
+
+impl<UserIdT, AmountT, PublicKeyT, SignatureT> Ledger<UserIdT, AmountT, PublicKeyT, SignatureT> {
+    /// Check the signatures of transfers with `verifier` from now on.
+    ///
+    /// Events already in the history aren't checked again.
+    /// By default, every signature is accepted, see [AlwaysAccept].
+    pub fn with_verifier(
+        mut self,
+        verifier: impl TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT> + 'static,
+    ) -> Self {
+        self.verifier = Arc::new(verifier);
+        self
+    }
+
+    /// If `strict`, refuse all transfers with [AcceptEventError::VerifierNotConfigured]
+    /// while the verifier doesn't actually check anything, like [AlwaysAccept].
+    ///
+    /// This guards against forgetting [Self::with_verifier]. Off by default.
+    pub fn with_strict_signatures(mut self, strict: bool) -> Self {
+        self.strict_signatures = strict;
+        self
+    }
+
+    /// Only accept mints signed by one of `authorities`, with
+    /// [AcceptEventError::UnauthorizedMint] for the rest.
+    ///
+    /// Mints are checked with [TransferVerifier::verify_mint], and a signed mint can't be
+    /// replayed, see [MintVerifierArgs::nonce].
+    /// By default, there are no authorities, and every mint is accepted.
+    pub fn with_mint_authorities(
+        mut self,
+        authorities: impl IntoIterator<Item = PublicKeyT>,
+    ) -> Self {
+        self.mint_authorities = authorities.into_iter().collect();
+        self
+    }
+
+    /// Alongside [Self::with_mint_authorities], accept one unsigned mint of at most `max`
+    /// in each block, as the reward for whoever mined it, see [Self::extend_block].
+    ///
+    /// Each height has its own reward, so this is the only way to mint without a signature,
+    /// and [Self::with_event] and friends still refuse every unsigned mint.
+    /// By default, blocks have no reward.
+    pub fn with_block_reward(mut self, max: AmountT) -> Self {
+        self.block_reward = Some(max);
+        self
+    }
+
+    /// Whether transfers are refused outright, see [Self::with_strict_signatures].
+    fn verifier_not_configured(&self) -> bool {
+        self.strict_signatures && self.verifier.is_no_op()
+    }
+
+    /// Reserve room for at least `capacity` more events, e.g before bulk-loading history
+    /// with [Self::import_events].
+    pub fn with_event_capacity(mut self, capacity: usize) -> Self {
+        self.events.reserve(capacity);
+        self
+    }
+
+    /// Walk the event history in order, accumulating into `init`.
+    ///
+    /// `f` is passed the index of each event in the history.
+    pub fn fold_events<B>(
+        &self,
+        init: B,
+        mut f: impl FnMut(B, usize, &LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>) -> B,
+    ) -> B {
+        self.events
+            .iter()
+            .enumerate()
+            .fold(init, |acc, (index, event)| f(acc, index, event))
+    }
+
+    /// How many of each kind of event are in this history.
+    pub fn events_by_kind(&self) -> EventCounts {
+        EventCounts {
+            new_users: self
+                .events
+                .iter()
+                .filter(|it| it.as_new_user().is_some())
+                .count(),
+            mints: self
+                .events
+                .iter()
+                .filter(|it| it.as_mint().is_some())
+                .count(),
+            transfers: self.transfers().count(),
+        }
+    }
+
+    /// All the [LedgerEvent::Transfer]s in this history, in order.
+    pub fn transfers(
+        &self,
+    ) -> impl Iterator<Item = &LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>> {
+        self.events.iter().filter(|it| it.as_transfer().is_some())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+pub struct EventCounts {
+    pub new_users: usize,
+    pub mints: usize,
+    pub transfers: usize,
+}
+
+/// The state of a [Ledger]'s accounts at some point, see [Ledger::snapshot].
+///
+/// Clones share the same storage, so are cheap to hand out to readers.
+#[derive(Debug)]
+pub struct LedgerSnapshot<UserIdT, AmountT, PublicKeyT> {
+    users: Arc<HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>>,
+}
+
+impl<UserIdT, AmountT, PublicKeyT> Clone for LedgerSnapshot<UserIdT, AmountT, PublicKeyT> {
+    fn clone(&self) -> Self {
+        Self {
+            users: self.users.clone(),
+        }
+    }
+}
+
+impl<UserIdT, AmountT, PublicKeyT> LedgerSnapshot<UserIdT, AmountT, PublicKeyT>
+where
+    UserIdT: Hash + Eq,
+{
+    pub fn balance_of(&self, user: &UserIdT) -> Option<&AmountT> {
+        self.users.get(user).map(UserSummary::balance)
+    }
+    pub fn users(&self) -> &HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>> {
+        &self.users
+    }
+}
+
+impl<AmountT, PublicKeyT> UserSummary<AmountT, PublicKeyT> {
+    pub fn balance(&self) -> &AmountT {
+        &self.balance
+    }
+    pub fn public_key(&self) -> &PublicKeyT {
+        &self.public_key
+    }
+    /// The nonce this user's next transfer must carry: the number of transfers they've sent.
+    ///
+    /// A transfer with any other nonce is refused with [AcceptEventError::WrongNonce], so a
+    /// signed transfer can't be replayed.
+    pub fn next_nonce(&self) -> u64 {
+        self.transfers_sent
+    }
+    /// The [MintVerifierArgs::nonce] for the next signed mint to this user: the number of signed
+    /// mints they've received.
+    pub fn next_mint_nonce(&self) -> u64 {
+        self.signed_mints_received
+    }
 }
 
 impl<UserIdT, AmountT, PublicKeyT, SignatureT> Ledger<UserIdT, AmountT, PublicKeyT, SignatureT>
@@ -24,65 +218,97 @@ where
 {
     /// Get the current state of all user balances according to this event history.
     ///
-    /// # Panics
-    /// - if internal consistency is compromised
-    ///
-    // This could be stored in the [Ledger] so we're not constantly recomputing it
-    // this is done on the `mutable-ledger` branch, but I'm sticking with this implementation
-    // because it allows us to reject an invalid block more easily.
+    /// This is kept up to date as events are added, so isn't recomputed, see
+    /// [Self::try_users].
     pub fn users(&self) -> HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>> {
-        self.events.iter().fold(HashMap::new(), |users, event| {
-            users.tap_mut(|users| match event {
-                LedgerEvent::NewUser {
-                    identifier,
-                    public_key,
-                } => {
-                    let clobbered = users
-                        .insert(
-                            identifier.clone(),
-                            UserSummary {
-                                balance: AmountT::zero(),
-                                public_key: public_key.clone(),
-                            },
-                        )
-                        .is_some();
-                    assert!(!clobbered, "duplicate users in history")
-                }
-                LedgerEvent::Mint {
-                    beneficiary,
-                    amount,
-                } => {
-                    let balance = &mut users
-                        .get_mut(beneficiary)
-                        .expect("no user for mint")
-                        .balance;
-                    *balance = balance.checked_add(amount).expect("mint overflows balance");
-                }
-                LedgerEvent::Transfer {
-                    benefactor,
-                    beneficiary,
-                    amount,
-                    benefactor_signature: _, // TODO(aatifsyed): check signature?
-                } => {
-                    let benefactor = &mut users
-                        .get_mut(benefactor)
-                        .expect("no benefactor for transfer")
-                        .balance;
-                    *benefactor = benefactor
-                        .checked_sub(amount)
-                        .expect("transfer overdraws benefactor");
-                    let beneficiary = &mut users
-                        .get_mut(beneficiary)
-                        .expect("no beneficiary for transfer")
-                        .balance;
-                    *beneficiary = beneficiary
-                        .checked_add(amount)
-                        .expect("transfer overflows beneficiary");
-                }
+        HashMap::clone(&self.users)
+    }
+
+    /// The [UserSummary::next_nonce] for `user`, or [None] if they don't have an account.
+    pub fn next_nonce(&self, user: &UserIdT) -> Option<u64> {
+        self.users.get(user).map(UserSummary::next_nonce)
+    }
+
+    /// The [UserSummary::next_mint_nonce] for `user`, or [None] if they don't have an account.
+    pub fn next_mint_nonce(&self, user: &UserIdT) -> Option<u64> {
+        self.users.get(user).map(UserSummary::next_mint_nonce)
+    }
+
+    /// Recompute [Self::users] from the whole event history, failing with the index of the
+    /// first inconsistent event.
+    ///
+    /// A history built with [Self::with_event] is always consistent, and agrees with
+    /// [Self::users].
+    pub fn try_users(
+        &self,
+    ) -> Result<HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>, (usize, AcceptEventError)> {
+        self.events
+            .iter()
+            .enumerate()
+            .try_fold(HashMap::new(), |mut users, (index, event)| {
+                apply_event(&mut users, event)
+                    .map(|_| users)
+                    .map_err(|error| (index, error))
             })
-        })
     }
 
+    /// Freeze the current state of the accounts.
+    ///
+    /// Later changes to this ledger aren't reflected in the snapshot.
+    pub fn snapshot(&self) -> LedgerSnapshot<UserIdT, AmountT, PublicKeyT> {
+        LedgerSnapshot {
+            users: self.users.clone(),
+        }
+    }
+
+    /// Accounts with a nonzero balance less than `threshold`, ordered by balance (then id).
+    ///
+    /// An account that's been drained to zero isn't dust.
+    pub fn dust_accounts(&self, threshold: AmountT) -> Vec<(UserIdT, AmountT)>
+    where
+        UserIdT: Ord,
+        AmountT: Ord,
+    {
+        let mut dust = self
+            .users
+            .iter()
+            .filter(|(_, summary)| !summary.balance.is_zero() && summary.balance < threshold)
+            .map(|(identifier, summary)| (identifier.clone(), summary.balance.clone()))
+            .collect::<Vec<_>>();
+        dust.sort_by(|(left_id, left), (right_id, right)| {
+            left.cmp(right).then_with(|| left_id.cmp(right_id))
+        });
+        dust
+    }
+
+    /// Forget the last `count` events of the history, e.g to undo blocks in a reorg.
+    ///
+    /// # Panics
+    /// - if there are fewer than `count` events
+    pub(crate) fn undo_events(&mut self, count: usize) {
+        let users = Arc::make_mut(&mut self.users);
+        for event in self.events.drain(self.events.len() - count..).rev() {
+            unapply_event(users, &event);
+        }
+    }
+
+    /// This history, followed by `event`, which has already been checked.
+    fn with_checked_event(
+        &self,
+        event: LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>,
+    ) -> Self
+    where
+        SignatureT: Clone,
+    {
+        let mut ledger = self.clone();
+        apply_event(Arc::make_mut(&mut ledger.users), &event).expect("event has been checked");
+        ledger.events.push(event);
+        ledger
+    }
+
+    /// This history, followed by `event`, without checking it or updating [Self::users],
+    /// for building inconsistent histories.
+    #[cfg(test)]
     fn with_event_unchecked(
         &self,
         event: LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>,
@@ -90,55 +316,118 @@ where
     where
         SignatureT: Clone,
     {
-        Self {
-            events: self.events.clone().tap_mut(|it| it.push(event)),
+        let mut ledger = self.clone();
+        ledger.events.push(event);
+        ledger
+    }
+
+    /// Fail with [AcceptEventError::UnauthorizedMint] unless there are no
+    /// [Self::with_mint_authorities], or one of them signed this mint with this `nonce`.
+    fn authorize_mint(
+        &self,
+        event_index: usize,
+        beneficiary: &UserIdT,
+        amount: &AmountT,
+        nonce: u64,
+        authority_signature: &Option<SignatureT>,
+    ) -> Result<(), AcceptEventError> {
+        if self.mint_authorities.is_empty() {
+            return Ok(());
+        }
+        if self.verifier_not_configured() {
+            return Err(AcceptEventError::VerifierNotConfigured);
+        }
+        let authorized = authority_signature
+            .as_ref()
+            .is_some_and(|authority_signature| {
+                self.mint_authorities.iter().any(|authority_public_key| {
+                    self.verifier.verify_mint(MintVerifierArgs {
+                        event_index,
+                        beneficiary,
+                        amount,
+                        nonce,
+                        authority_public_key,
+                        authority_signature,
+                    })
+                })
+            });
+        match authorized {
+            true => Ok(()),
+            false => Err(AcceptEventError::UnauthorizedMint),
         }
     }
 
-    /// Fail with [AcceptEventError::NoSuchAccount] or [AcceptEventError::WouldOverflow] as appropriate
+    /// Fail with [AcceptEventError::NoSuchBeneficiary] or [AcceptEventError::WouldOverflow] as appropriate
     fn could_receive(
         &self,
         beneficiary: &UserIdT,
         amount: &AmountT,
     ) -> Result<UserSummary<AmountT, PublicKeyT>, AcceptEventError> {
-        match self.users().get(beneficiary) {
+        match self.users.get(beneficiary) {
             Some(user_summary) => match user_summary.balance.checked_add(amount) {
                 Some(_) => Ok(user_summary.clone()),
                 None => Err(AcceptEventError::WouldOverflow),
             },
-            None => Err(AcceptEventError::NoSuchAccount),
+            None => Err(AcceptEventError::NoSuchBeneficiary),
         }
     }
 
-    /// Fail with [AcceptEventError::NoSuchAccount] or [AcceptEventError::WouldOverdraw] as appropriate.
+    /// Fail with [AcceptEventError::NoSuchBenefactor] or [AcceptEventError::WouldOverdraw] as appropriate.
+    ///
+    /// Sending the whole balance is fine, and leaves the account at zero (but still registered).
     fn could_send(
         &self,
         benefactor: &UserIdT,
         amount: &AmountT,
     ) -> Result<UserSummary<AmountT, PublicKeyT>, AcceptEventError> {
-        match self.users().get(benefactor) {
+        match self.users.get(benefactor) {
             Some(user_summary) => match user_summary.balance.checked_sub(amount) {
                 Some(_) => Ok(user_summary.clone()),
                 None => Err(AcceptEventError::WouldOverdraw), // AmountT: num::Unsigned
             },
-            None => Err(AcceptEventError::NoSuchAccount),
+            None => Err(AcceptEventError::NoSuchBenefactor),
         }
     }
 
-    // TODO(vectorised version)
-    pub fn with_event<BlockIdT>(
+    /// A ledger which registers `accounts`, and then performs `mints`.
+    ///
+    /// Every event goes through [Self::with_event], so this fails like ingestion would,
+    /// with the index of the first bad event, e.g on a duplicate id or a mint to an
+    /// account that isn't registered.
+    pub fn genesis_signed(
+        accounts: impl IntoIterator<Item = (UserIdT, PublicKeyT)>,
+        mints: impl IntoIterator<Item = (UserIdT, AmountT)>,
+    ) -> Result<Self, (usize, AcceptEventError)>
+    where
+        SignatureT: Clone,
+    {
+        accounts
+            .into_iter()
+            .map(|(identifier, public_key)| LedgerEvent::NewUser {
+                identifier,
+                public_key,
+            })
+            .chain(
+                mints
+                    .into_iter()
+                    .map(|(beneficiary, amount)| LedgerEvent::Mint {
+                        beneficiary,
+                        amount,
+                        authority_signature: None,
+                    }),
+            )
+            .enumerate()
+            .try_fold(Self::default(), |ledger, (index, event)| {
+                ledger.with_event(event).map_err(|error| (index, error))
+            })
+    }
+
+    /// This history, followed by `event`, if it's valid.
+    ///
+    /// Transfers are checked with the ledger's [TransferVerifier].
+    pub fn with_event(
         &self,
         event: LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>,
-
-        // This is a bit of a quick and dirty implementation detail leaked to the outside.
-        // Really we should have a TransferVerifierT: TransferVerifier on the Ledger, since verification is fixed for a ledger.
-        // We could then impl TransferVerifier for e.g FnMut(...) -> bool.
-        // For now, keep in this function.
-        block_id: BlockIdT,
-        event_index: usize,
-        transfer_verifier: impl FnOnce(
-            TransferVerifierArgs<BlockIdT, &UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
-        ) -> Result<(), ()>,
     ) -> Result<Self, AcceptEventError>
     where
         SignatureT: Clone,
@@ -147,63 +436,626 @@ where
             LedgerEvent::NewUser {
                 identifier,
                 public_key: _,
-            } => match self.users().contains_key(identifier) {
+            } => match self.users.contains_key(identifier) {
                 true => Err(AcceptEventError::UserIdTaken),
-                false => Ok(self.with_event_unchecked(event)),
+                false => Ok(self.with_checked_event(event)),
             },
             LedgerEvent::Mint {
                 beneficiary,
                 amount,
+                authority_signature,
             } => {
+                let nonce = self
+                    .users
+                    .get(beneficiary)
+                    .map_or(0, UserSummary::next_mint_nonce);
+                self.authorize_mint(
+                    self.events.len(),
+                    beneficiary,
+                    amount,
+                    nonce,
+                    authority_signature,
+                )?;
                 self.could_receive(beneficiary, amount)?;
-                Ok(self.with_event_unchecked(event))
+                Ok(self.with_checked_event(event))
             }
             LedgerEvent::Transfer {
                 benefactor,
                 beneficiary,
                 amount,
+                nonce,
                 benefactor_signature,
             } => {
-                self.could_receive(beneficiary, amount)?;
-                let benefactor_public_key = &self.could_send(benefactor, amount)?.public_key;
-                transfer_verifier(TransferVerifierArgs {
-                    block_id,
-                    event_index,
+                if benefactor == beneficiary {
+                    return Err(AcceptEventError::SelfTransfer);
+                }
+                let benefactor_summary = self
+                    .users
+                    .get(benefactor)
+                    .ok_or(AcceptEventError::NoSuchBenefactor)?;
+                let benefactor_public_key = &benefactor_summary.public_key;
+                if !self.users.contains_key(beneficiary) {
+                    return Err(AcceptEventError::NoSuchBeneficiary);
+                }
+                // Check the signature before the balances, so that a transfer which just
+                // can't be afforded yet isn't confused with one that will never be valid
+                if self.verifier_not_configured() {
+                    return Err(AcceptEventError::VerifierNotConfigured);
+                }
+                let verified = self.verifier.verify(TransferVerifierArgs {
+                    event_index: self.events.len(),
                     benefactor,
                     beneficiary,
                     amount,
+                    nonce: *nonce,
                     benefactor_public_key,
                     benefactor_signature,
-                })
-                .map_err(|_| AcceptEventError::InvalidSignature)?;
-                Ok(self.with_event_unchecked(event))
+                });
+                if !verified {
+                    return Err(AcceptEventError::InvalidSignature);
+                }
+                check_nonce(benefactor_summary, *nonce)?;
+                self.could_receive(beneficiary, amount)?;
+                self.could_send(benefactor, amount)?;
+                Ok(self.with_checked_event(event))
+            }
+        }
+    }
+}
+
+impl<UserIdT, AmountT, PublicKeyT, SignatureT> Ledger<UserIdT, AmountT, PublicKeyT, SignatureT>
+where
+    UserIdT: Hash + Eq + Clone + Sync,
+    AmountT:
+        Clone + PartialOrd + num::CheckedAdd + num::CheckedSub + num::Zero + num::Unsigned + Sync,
+    PublicKeyT: Clone + Sync,
+    SignatureT: Clone + Sync,
+{
+    /// Like calling [Self::with_event] for each of `events` in turn, but with the signatures
+    /// checked up front, on `num_threads` threads.
+    ///
+    /// Fails with the index of the first bad event.
+    /// Bad signatures are found first, so if an event has a bad signature, the
+    /// [AcceptEventError::InvalidSignature] for it (or an earlier one) is returned
+    /// even if there's a different problem with an earlier event.
+    pub fn with_events(
+        &self,
+        events: &[LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>],
+        num_threads: NonZeroUsize,
+    ) -> Result<Self, (usize, AcceptEventError)> {
+        let mut ledger = self.clone();
+        ledger.extend_events(events, num_threads)?;
+        Ok(ledger)
+    }
+
+    /// Like [Self::with_events], but extends this ledger in place, rather than copying
+    /// its history.
+    ///
+    /// If any event is bad, the ledger is left as it was.
+    pub fn extend_events(
+        &mut self,
+        events: &[LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>],
+        num_threads: NonZeroUsize,
+    ) -> Result<(), (usize, AcceptEventError)> {
+        self.append(events, num_threads, false)
+    }
+
+    /// Like [Self::extend_events], for the events of a block at the next height, which may
+    /// have a reward, see [Self::with_block_reward].
+    pub fn extend_block(
+        &mut self,
+        events: &[LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>],
+        num_threads: NonZeroUsize,
+    ) -> Result<(), (usize, AcceptEventError)> {
+        self.append(events, num_threads, true)
+    }
+
+    /// [Self::extend_events], or [Self::extend_block] if `has_reward`.
+    fn append(
+        &mut self,
+        events: &[LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>],
+        num_threads: NonZeroUsize,
+        has_reward: bool,
+    ) -> Result<(), (usize, AcceptEventError)> {
+        self.verify_signatures(events, num_threads, has_reward)?;
+
+        // Now the balances, in order.
+        // This is the same as calling [Self::with_event], but without copying the ledger each time
+        let _span = validation_span!("apply_events", events = events.len());
+        self.events.reserve(events.len());
+        let applied = events
+            .iter()
+            .enumerate()
+            .try_for_each(|(event_index, event)| {
+                apply_event(Arc::make_mut(&mut self.users), event)
+                    .map_err(|error| (event_index, error))?;
+                self.events.push(event.clone());
+                Ok(())
+            });
+        if let Err((event_index, _)) = applied {
+            self.undo_events(event_index);
+        }
+        applied
+    }
+
+    /// Like calling [Self::with_event] for each of `events` in turn, but extends this
+    /// ledger in place, so is much cheaper for long histories, see [Self::extend_events].
+    ///
+    /// Fails with the index (in `events`) of the first bad event.
+    pub fn import_events(
+        mut self,
+        events: impl IntoIterator<Item = LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>>,
+    ) -> Result<Self, (usize, AcceptEventError)> {
+        let events = events.into_iter().collect::<Vec<_>>();
+        self.extend_events(&events, NonZeroUsize::MIN)?;
+        Ok(self)
+    }
+
+    /// A ledger with the events of all of `blocks`, in order, reserving room for them up front.
+    ///
+    /// Signatures aren't checked (see [AlwaysAccept]), so this is for blocks which have
+    /// already been validated.
+    /// Use [Self::with_verifier] and [Self::import_events] for untrusted blocks.
+    ///
+    /// Fails with the index of the first bad block, and of the bad event in that block.
+    pub fn from_blocks<BlockIdT>(
+        blocks: &[Block<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT>],
+    ) -> Result<Self, (usize, usize, AcceptEventError)> {
+        let capacity = blocks.iter().map(|it| it.events.len()).sum();
+        let mut ledger = Self::default().with_event_capacity(capacity);
+        for (block_index, block) in blocks.iter().enumerate() {
+            ledger
+                .extend_block(&block.events, NonZeroUsize::MIN)
+                .map_err(|(event_index, error)| (block_index, event_index, error))?;
+        }
+        Ok(ledger)
+    }
+
+    /// The signature checks of [Self::with_events], for all of `events` at once.
+    ///
+    /// If `has_reward`, the first unsigned mint small enough to be the block reward is exempt
+    /// from the mint authorities.
+    fn verify_signatures(
+        &self,
+        events: &[LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>],
+        num_threads: NonZeroUsize,
+        has_reward: bool,
+    ) -> Result<(), (usize, AcceptEventError)> {
+        // Signatures don't depend on balances, just on who's registered.
+        // If a benefactor doesn't exist, the sequential pass will catch it.
+        // Existing users are looked up as they are, so only this batch's new users are collected.
+        let mut new_users = HashMap::new();
+        for event in events {
+            if let LedgerEvent::NewUser {
+                identifier,
+                public_key,
+            } = event
+            {
+                new_users.entry(identifier).or_insert(public_key);
+            }
+        }
+        let transfers = events
+            .iter()
+            .enumerate()
+            .filter_map(|(event_index, event)| match event {
+                LedgerEvent::Transfer { benefactor, .. } => {
+                    let public_key = match self.users.get(benefactor) {
+                        Some(summary) => &summary.public_key,
+                        None => *new_users.get(benefactor)?,
+                    };
+                    Some((event_index, event, public_key))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        {
+            let _span = validation_span!("verify_signatures", transfers = transfers.len());
+            if let (true, Some((event_index, ..))) =
+                (self.verifier_not_configured(), transfers.first())
+            {
+                return Err((*event_index, AcceptEventError::VerifierNotConfigured));
+            }
+
+            let first_bad = AtomicUsize::new(usize::MAX);
+            // Just the verifier is shared between threads, not the accounts
+            let (verifier, offset) = (&self.verifier, self.events.len());
+            let verify_chunk = |chunk: &[(usize, &LedgerEvent<_, _, _, _>, &PublicKeyT)]| {
+                for (event_index, event, benefactor_public_key) in chunk {
+                    // chunks are in order, so the rest of this one can't be any earlier
+                    if *event_index > first_bad.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let LedgerEvent::Transfer {
+                        benefactor,
+                        beneficiary,
+                        amount,
+                        nonce,
+                        benefactor_signature,
+                    } = event
+                    else {
+                        unreachable!("only transfers are collected")
+                    };
+                    let verified = verifier.verify(TransferVerifierArgs {
+                        event_index: offset + event_index,
+                        benefactor,
+                        beneficiary,
+                        amount,
+                        nonce: *nonce,
+                        benefactor_public_key,
+                        benefactor_signature,
+                    });
+                    if !verified {
+                        first_bad.fetch_min(*event_index, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            };
+            match num_threads.get().min(transfers.len()) {
+                0 | 1 => verify_chunk(&transfers),
+                num_threads => std::thread::scope(|scope| {
+                    for chunk in transfers.chunks(transfers.len().div_ceil(num_threads)) {
+                        scope.spawn(|| verify_chunk(chunk));
+                    }
+                }),
             }
+            match first_bad.into_inner() {
+                usize::MAX => {}
+                event_index => return Err((event_index, AcceptEventError::InvalidSignature)),
+            }
+
+            // There are few enough mints that they aren't worth spreading across threads
+            let mut has_reward = has_reward;
+            // signed mints earlier in this batch, which the nonces carry on from
+            let mut signed_mints = HashMap::new();
+            for (event_index, event) in events.iter().enumerate() {
+                if let LedgerEvent::Mint {
+                    beneficiary,
+                    amount,
+                    authority_signature,
+                } = event
+                {
+                    let is_reward = authority_signature.is_none()
+                        && self.block_reward.as_ref().is_some_and(|max| amount <= max);
+                    if has_reward && is_reward {
+                        has_reward = false;
+                        continue;
+                    }
+                    let received = signed_mints.entry(beneficiary).or_insert(0);
+                    let nonce = self
+                        .users
+                        .get(beneficiary)
+                        .map_or(0, UserSummary::next_mint_nonce)
+                        + *received;
+                    self.authorize_mint(
+                        self.events.len() + event_index,
+                        beneficiary,
+                        amount,
+                        nonce,
+                        authority_signature,
+                    )
+                    .map_err(|error| (event_index, error))?;
+                    if authority_signature.is_some() {
+                        *received += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Update `users` for `event`, failing as [Ledger::with_event] would (apart from signatures).
+///
+/// On failure, `users` is left as it was.
+fn apply_event<UserIdT, AmountT, PublicKeyT, SignatureT>(
+    users: &mut HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>,
+    event: &LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>,
+) -> Result<(), AcceptEventError>
+where
+    UserIdT: Hash + Eq + Clone,
+    AmountT: num::CheckedAdd + num::CheckedSub + num::Zero,
+    PublicKeyT: Clone,
+{
+    #[cfg(test)]
+    EVENTS_FOLDED.with(|it| it.set(it.get() + 1));
+    match event {
+        LedgerEvent::NewUser {
+            identifier,
+            public_key,
+        } => match users.contains_key(identifier) {
+            true => Err(AcceptEventError::UserIdTaken),
+            false => {
+                users.insert(
+                    identifier.clone(),
+                    UserSummary {
+                        balance: AmountT::zero(),
+                        public_key: public_key.clone(),
+                        transfers_sent: 0,
+                        signed_mints_received: 0,
+                    },
+                );
+                Ok(())
+            }
+        },
+        LedgerEvent::Mint {
+            beneficiary,
+            amount,
+            authority_signature,
+        } => {
+            credit(users, beneficiary, amount)?;
+            if authority_signature.is_some() {
+                let receiver = users.get_mut(beneficiary).expect("beneficiary exists");
+                receiver.signed_mints_received += 1;
+            }
+            Ok(())
+        }
+        LedgerEvent::Transfer {
+            benefactor,
+            beneficiary,
+            amount,
+            nonce,
+            benefactor_signature: _,
+        } => {
+            if benefactor == beneficiary {
+                return Err(AcceptEventError::SelfTransfer);
+            }
+            let sender = users
+                .get(benefactor)
+                .ok_or(AcceptEventError::NoSuchBenefactor)?;
+            // check both sides before touching either, so a failure leaves `users` alone
+            let receiver = users
+                .get(beneficiary)
+                .ok_or(AcceptEventError::NoSuchBeneficiary)?;
+            check_nonce(sender, *nonce)?;
+            let credited = receiver
+                .balance
+                .checked_add(amount)
+                .ok_or(AcceptEventError::WouldOverflow)?;
+            let sender = users.get_mut(benefactor).expect("benefactor exists");
+            sender.balance = sender
+                .balance
+                .checked_sub(amount)
+                .ok_or(AcceptEventError::WouldOverdraw)?;
+            sender.transfers_sent += 1;
+            users
+                .get_mut(beneficiary)
+                .expect("beneficiary exists")
+                .balance = credited;
+            Ok(())
         }
     }
 }
 
-pub struct TransferVerifierArgs<BlockIdT, UserIdT, AmountT, PublicKeyT, SignatureT> {
-    pub block_id: BlockIdT,
+/// Undo [apply_event] for `event`, the last event applied to `users`.
+fn unapply_event<UserIdT, AmountT, PublicKeyT, SignatureT>(
+    users: &mut HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>,
+    event: &LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT>,
+) where
+    UserIdT: Hash + Eq,
+    AmountT: num::CheckedAdd + num::CheckedSub,
+{
+    #[cfg(test)]
+    EVENTS_FOLDED.with(|it| it.set(it.get() + 1));
+    let applied = "event was the last one applied";
+    match event {
+        LedgerEvent::NewUser { identifier, .. } => {
+            users.remove(identifier).expect(applied);
+        }
+        LedgerEvent::Mint {
+            beneficiary,
+            amount,
+            authority_signature,
+        } => {
+            debit(users, beneficiary, amount).expect(applied);
+            if authority_signature.is_some() {
+                users
+                    .get_mut(beneficiary)
+                    .expect(applied)
+                    .signed_mints_received -= 1;
+            }
+        }
+        LedgerEvent::Transfer {
+            benefactor,
+            beneficiary,
+            amount,
+            ..
+        } => {
+            debit(users, beneficiary, amount).expect(applied);
+            credit(users, benefactor, amount).expect(applied);
+            users.get_mut(benefactor).expect(applied).transfers_sent -= 1;
+        }
+    }
+}
+
+/// Fail with [AcceptEventError::WrongNonce] unless `nonce` is the sender's
+/// [UserSummary::next_nonce].
+fn check_nonce<AmountT, PublicKeyT>(
+    sender: &UserSummary<AmountT, PublicKeyT>,
+    nonce: u64,
+) -> Result<(), AcceptEventError> {
+    match sender.next_nonce() == nonce {
+        true => Ok(()),
+        false => Err(AcceptEventError::WrongNonce {
+            expected: sender.next_nonce(),
+            found: nonce,
+        }),
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// How many times [apply_event] or [unapply_event] has run on this thread, for checking
+    /// that work isn't repeated.
+    pub(crate) static EVENTS_FOLDED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn credit<UserIdT, AmountT, PublicKeyT>(
+    users: &mut HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>,
+    user: &UserIdT,
+    amount: &AmountT,
+) -> Result<(), AcceptEventError>
+where
+    UserIdT: Hash + Eq,
+    AmountT: num::CheckedAdd,
+{
+    let balance = &mut users
+        .get_mut(user)
+        .ok_or(AcceptEventError::NoSuchBeneficiary)?
+        .balance;
+    *balance = balance
+        .checked_add(amount)
+        .ok_or(AcceptEventError::WouldOverflow)?;
+    Ok(())
+}
+
+fn debit<UserIdT, AmountT, PublicKeyT>(
+    users: &mut HashMap<UserIdT, UserSummary<AmountT, PublicKeyT>>,
+    user: &UserIdT,
+    amount: &AmountT,
+) -> Result<(), AcceptEventError>
+where
+    UserIdT: Hash + Eq,
+    AmountT: num::CheckedSub,
+{
+    let balance = &mut users
+        .get_mut(user)
+        .ok_or(AcceptEventError::NoSuchBenefactor)?
+        .balance;
+    *balance = balance
+        .checked_sub(amount)
+        .ok_or(AcceptEventError::WouldOverdraw)?;
+    Ok(())
+}
+
+pub struct MintVerifierArgs<UserIdT, AmountT, PublicKeyT, SignatureT> {
+    /// Where the mint will be in the [Ledger]'s history.
+    pub event_index: usize,
+    pub beneficiary: UserIdT,
+    pub amount: AmountT,
+    /// The beneficiary's [UserSummary::next_mint_nonce], which the authority should have
+    /// signed, so that the mint can't be replayed.
+    pub nonce: u64,
+    /// One of the [Ledger::with_mint_authorities].
+    pub authority_public_key: PublicKeyT,
+    pub authority_signature: SignatureT,
+}
+
+pub struct TransferVerifierArgs<UserIdT, AmountT, PublicKeyT, SignatureT> {
+    /// Where the transfer will be in the [Ledger]'s history.
     pub event_index: usize,
     pub benefactor: UserIdT,
     pub beneficiary: UserIdT,
     pub amount: AmountT,
+    /// See [UserSummary::next_nonce].
+    pub nonce: u64,
     pub benefactor_public_key: PublicKeyT,
     pub benefactor_signature: SignatureT,
 }
 
+/// Checks that the benefactor of a [LedgerEvent::Transfer] authorised it, see
+/// [Ledger::with_verifier].
+pub trait TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT>:
+    fmt::Debug + Send + Sync
+{
+    /// Whether the benefactor authorised this transfer.
+    fn verify(
+        &self,
+        args: TransferVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool;
+
+    /// Whether this authority authorised this mint, see [Ledger::with_mint_authorities].
+    ///
+    /// Refuses every mint by default.
+    fn verify_mint(
+        &self,
+        args: MintVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool {
+        let _ = args;
+        false
+    }
+
+    /// Whether this verifier accepts everything without looking, see
+    /// [Ledger::with_strict_signatures].
+    fn is_no_op(&self) -> bool {
+        false
+    }
+}
+
+impl<T, UserIdT, AmountT, PublicKeyT, SignatureT>
+    TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT> for Arc<T>
+where
+    T: TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT> + ?Sized,
+{
+    fn verify(
+        &self,
+        args: TransferVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool {
+        T::verify(self, args)
+    }
+
+    fn verify_mint(
+        &self,
+        args: MintVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool {
+        T::verify_mint(self, args)
+    }
+
+    fn is_no_op(&self) -> bool {
+        T::is_no_op(self)
+    }
+}
+
+/// Accepts every signature.
+///
+/// Useful for tests, and for replaying history which has already been checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AlwaysAccept;
+
+impl<UserIdT, AmountT, PublicKeyT, SignatureT>
+    TransferVerifier<UserIdT, AmountT, PublicKeyT, SignatureT> for AlwaysAccept
+{
+    fn verify(
+        &self,
+        _: TransferVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool {
+        true
+    }
+
+    fn verify_mint(
+        &self,
+        _: MintVerifierArgs<&UserIdT, &AmountT, &PublicKeyT, &SignatureT>,
+    ) -> bool {
+        true
+    }
+
+    fn is_no_op(&self) -> bool {
+        true
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum AcceptEventError {
     #[error("a user with the requested identifier already exists")]
     UserIdTaken,
     #[error("would overdraw an account")]
     WouldOverdraw,
-    #[error("an account in this event does not exist")]
-    NoSuchAccount,
+    #[error("the account sending money does not exist")]
+    NoSuchBenefactor,
+    #[error("the account receiving money does not exist")]
+    NoSuchBeneficiary,
     #[error("an account balance would overflow")]
     WouldOverflow,
     #[error("invalid signature for transfer")]
     InvalidSignature,
+    #[error("benefactor and beneficiary of a transfer are the same")]
+    SelfTransfer,
+    #[error("transfers are refused until a verifier is configured")]
+    VerifierNotConfigured,
+    #[error("mint wasn't signed by a minting authority")]
+    UnauthorizedMint,
+    #[error("transfer has nonce {found}, but the benefactor's next nonce is {expected}")]
+    WrongNonce { expected: u64, found: u64 },
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, enum_as_inner::EnumAsInner)]
@@ -216,11 +1068,637 @@ pub enum LedgerEvent<UserIdT, AmountT, PublicKeyT, SignatureT> {
     Mint {
         beneficiary: UserIdT,
         amount: AmountT,
+        /// Only needed if the ledger has [Ledger::with_mint_authorities].
+        authority_signature: Option<SignatureT>,
     },
     Transfer {
         benefactor: UserIdT,
         beneficiary: UserIdT,
         amount: AmountT,
+        /// The benefactor's [UserSummary::next_nonce].
+        nonce: u64,
         benefactor_signature: SignatureT,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tap::Tap as _;
+
+    type TestLedger = Ledger<char, u64, (), ()>;
+    type TestEvent = LedgerEvent<char, u64, (), ()>;
+
+    fn new_user(identifier: char) -> TestEvent {
+        LedgerEvent::NewUser {
+            identifier,
+            public_key: (),
+        }
+    }
+
+    fn mint(beneficiary: char, amount: u64) -> TestEvent {
+        LedgerEvent::Mint {
+            beneficiary,
+            amount,
+            authority_signature: None,
+        }
+    }
+
+    fn transfer(benefactor: char, beneficiary: char, amount: u64, nonce: u64) -> TestEvent {
+        LedgerEvent::Transfer {
+            benefactor,
+            beneficiary,
+            amount,
+            nonce,
+            benefactor_signature: (),
+        }
+    }
+
+    fn ledger(events: impl IntoIterator<Item = TestEvent>) -> TestLedger {
+        events
+            .into_iter()
+            .fold(TestLedger::default(), |ledger, event| {
+                ledger.with_event(event).unwrap()
+            })
+    }
+
+    #[test]
+    fn next_nonce() {
+        let ledger = ledger([new_user('a'), new_user('b'), mint('a', 10)]);
+        assert_eq!(ledger.next_nonce(&'a'), Some(0));
+        assert_eq!(ledger.next_nonce(&'c'), None);
+
+        let ledger = ledger.with_event(transfer('a', 'b', 3, 0)).unwrap();
+        assert_eq!(ledger.next_nonce(&'a'), Some(1));
+        assert_eq!(ledger.next_nonce(&'b'), Some(0));
+
+        // refused transfers don't count
+        assert!(ledger.with_event(transfer('a', 'b', 100, 1)).is_err());
+        let ledger = ledger.with_event(transfer('a', 'b', 3, 1)).unwrap();
+        assert_eq!(ledger.next_nonce(&'a'), Some(2));
+
+        // transfers must carry the next nonce, so can't be replayed
+        for found in [1, 3] {
+            assert!(matches!(
+                ledger.with_event(transfer('a', 'b', 1, found)),
+                Err(AcceptEventError::WrongNonce { expected: 2, found: it }) if it == found
+            ));
+        }
+        assert!(matches!(
+            ledger.with_events(
+                &[transfer('a', 'b', 1, 2), transfer('a', 'b', 1, 2)],
+                NonZeroUsize::MIN
+            ),
+            Err((
+                1,
+                AcceptEventError::WrongNonce {
+                    expected: 3,
+                    found: 2
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn events_by_kind() {
+        let ledger = ledger([
+            new_user('a'),
+            new_user('b'),
+            mint('a', 10),
+            transfer('a', 'b', 3, 0),
+            new_user('c'),
+            transfer('b', 'c', 1, 0),
+        ]);
+        assert_eq!(
+            ledger.events_by_kind(),
+            EventCounts {
+                new_users: 3,
+                mints: 1,
+                transfers: 2,
+            }
+        );
+        assert_eq!(
+            ledger.transfers().copied().collect::<Vec<_>>(),
+            [transfer('a', 'b', 3, 0), transfer('b', 'c', 1, 0)]
+        );
+    }
+
+    #[test]
+    fn self_transfer_is_rejected() {
+        let ledger = ledger([new_user('a'), new_user('b'), mint('a', 10)]);
+        assert!(matches!(
+            ledger.with_event(transfer('a', 'a', 1, 0)),
+            Err(AcceptEventError::SelfTransfer)
+        ));
+        assert!(ledger.with_event(transfer('a', 'b', 1, 0)).is_ok());
+    }
+
+    #[test]
+    fn fold_events_total_volume() {
+        let ledger = ledger([
+            new_user('a'),
+            new_user('b'),
+            mint('a', 10),
+            transfer('a', 'b', 3, 0),
+            transfer('b', 'a', 2, 0),
+        ]);
+        let volume = ledger.fold_events(0, |volume, _, event| match event {
+            LedgerEvent::Transfer { amount, .. } => volume + amount,
+            _ => volume,
+        });
+        assert_eq!(volume, 5);
+        let indices = ledger.fold_events(vec![], |indices, index, _| {
+            indices.tap_mut(|it| it.push(index))
+        });
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn users_agrees_with_try_users() {
+        let ledger = ledger([
+            new_user('a'),
+            new_user('b'),
+            mint('a', 10),
+            transfer('a', 'b', 3, 0),
+        ]);
+        assert_eq!(ledger.users(), ledger.try_users().unwrap());
+
+        let inconsistent = ledger.with_event_unchecked(transfer('b', 'a', 100, 0));
+        assert!(matches!(
+            inconsistent.try_users(),
+            Err((4, AcceptEventError::WouldOverdraw))
+        ));
+    }
+
+    #[test]
+    fn transfer_whole_balance() {
+        let before = ledger([new_user('a'), new_user('b'), mint('a', 10)]);
+        let after = before.with_event(transfer('a', 'b', 10, 0)).unwrap();
+        assert_eq!(after.users()[&'a'].balance, 0);
+        assert_eq!(after.users()[&'b'].balance, 10);
+        assert_eq!(after.dust_accounts(u64::MAX), [('b', 10)]);
+        assert!(matches!(
+            after.with_event(transfer('a', 'b', 1, 1)),
+            Err(AcceptEventError::WouldOverdraw)
+        ));
+        // a drained account can still receive
+        assert!(after.with_event(transfer('b', 'a', 1, 0)).is_ok());
+
+        let batched = before
+            .with_events(&[transfer('a', 'b', 10, 0)], NonZeroUsize::MIN)
+            .unwrap();
+        assert_eq!(batched.users(), after.users());
+        assert!(matches!(
+            before.with_events(&[transfer('a', 'b', 11, 0)], NonZeroUsize::MIN),
+            Err((0, AcceptEventError::WouldOverdraw))
+        ));
+    }
+
+    #[test]
+    fn users_are_kept_up_to_date() {
+        let mut ledger = ledger([new_user('a'), new_user('b'), mint('a', 10)]);
+        let folded = EVENTS_FOLDED.get();
+        let after = ledger.with_event(transfer('a', 'b', 3, 0)).unwrap();
+        assert_eq!(EVENTS_FOLDED.get() - folded, 1);
+        assert_eq!(after.users(), after.try_users().unwrap());
+
+        // a bad batch is left out entirely
+        let before = ledger.users();
+        assert!(matches!(
+            ledger.extend_events(
+                &[
+                    mint('b', 1),
+                    transfer('a', 'b', 3, 0),
+                    transfer('b', 'a', 100, 0)
+                ],
+                NonZeroUsize::MIN
+            ),
+            Err((2, AcceptEventError::WouldOverdraw))
+        ));
+        assert_eq!(ledger.users(), before);
+        assert_eq!(ledger.events_by_kind().transfers, 0);
+
+        ledger
+            .extend_events(
+                &[new_user('c'), transfer('a', 'c', 4, 0)],
+                NonZeroUsize::MIN,
+            )
+            .unwrap();
+        assert_eq!(ledger.users(), ledger.try_users().unwrap());
+        ledger.undo_events(2);
+        assert_eq!(ledger.users(), before);
+    }
+
+    #[test]
+    fn snapshot_is_frozen() {
+        let ledger = ledger([new_user('a'), new_user('b'), mint('a', 10)]);
+        let snapshot = ledger.snapshot();
+        let ledger = ledger.with_event(transfer('a', 'b', 4, 0)).unwrap();
+        assert_eq!(snapshot.balance_of(&'a'), Some(&10));
+        assert_eq!(snapshot.balance_of(&'b'), Some(&0));
+        assert_eq!(snapshot.balance_of(&'c'), None);
+        assert_eq!(ledger.snapshot().balance_of(&'a'), Some(&6));
+
+        let reader = snapshot.clone();
+        assert!(Arc::ptr_eq(&reader.users, &snapshot.users));
+        let users = std::thread::spawn(move || reader.users().clone())
+            .join()
+            .unwrap();
+        assert_eq!(&users, snapshot.users());
+    }
+
+    #[test]
+    fn dust_accounts() {
+        let ledger = ledger([
+            new_user('a'),
+            new_user('b'),
+            new_user('c'),
+            new_user('d'),
+            new_user('e'),
+            mint('a', 100),
+            mint('b', 3),
+            mint('c', 10),
+            mint('e', 3),
+        ]);
+        assert_eq!(ledger.dust_accounts(10), [('b', 3), ('e', 3)]);
+        assert_eq!(ledger.dust_accounts(11), [('b', 3), ('e', 3), ('c', 10)]);
+        assert_eq!(ledger.dust_accounts(0), []);
+    }
+
+    #[test]
+    fn genesis_signed() {
+        let ledger =
+            TestLedger::genesis_signed([('a', ()), ('b', ())], [('a', 10), ('b', 5), ('a', 1)])
+                .unwrap();
+        assert_eq!(ledger.users()[&'a'].balance, 11);
+        assert_eq!(ledger.users()[&'b'].balance, 5);
+
+        assert!(matches!(
+            TestLedger::genesis_signed([('a', ()), ('b', ()), ('a', ())], []),
+            Err((2, AcceptEventError::UserIdTaken))
+        ));
+        assert!(matches!(
+            TestLedger::genesis_signed([('a', ())], [('a', 10), ('b', 5)]),
+            Err((2, AcceptEventError::NoSuchBeneficiary))
+        ));
+        assert!(matches!(
+            TestLedger::genesis_signed([('a', ())], [('a', u64::MAX), ('a', 1)]),
+            Err((2, AcceptEventError::WouldOverflow))
+        ));
+    }
+
+    #[test]
+    fn with_events_agrees_with_with_event() {
+        use rand::{seq::SliceRandom as _, Rng as _, SeedableRng as _};
+        let ids = ['a', 'b', 'c', 'd'];
+        let amounts = [0, 1, 5, u64::MAX];
+        for seed in 0..200 {
+            let rng = &mut rand::rngs::StdRng::seed_from_u64(seed);
+            let events = (0..rng.gen_range(0..20))
+                .map(|_| {
+                    let (one, other) = (*ids.choose(rng).unwrap(), *ids.choose(rng).unwrap());
+                    match rng.gen_range(0..3) {
+                        0 => new_user(one),
+                        1 => mint(one, *amounts.choose(rng).unwrap()),
+                        _ => transfer(
+                            one,
+                            other,
+                            *amounts.choose(rng).unwrap(),
+                            rng.gen_range(0..2),
+                        ),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let sequential = events.iter().enumerate().try_fold(
+                TestLedger::default(),
+                |ledger, (event_index, event)| {
+                    ledger
+                        .with_event(*event)
+                        .map_err(|error| (event_index, error))
+                },
+            );
+            let batched = TestLedger::default().with_events(&events, NonZeroUsize::MIN);
+            let imported = TestLedger::default().import_events(events.iter().copied());
+            for other in [batched, imported] {
+                match (&sequential, other) {
+                    (Ok(sequential), Ok(other)) => {
+                        assert_eq!(sequential.events, other.events, "seed {seed}");
+                        assert_eq!(sequential.users(), other.users(), "seed {seed}");
+                    }
+                    (sequential, other) => assert_eq!(
+                        format!("{:?}", sequential.as_ref().err()),
+                        format!("{:?}", other.err()),
+                        "seed {seed}"
+                    ),
+                }
+            }
+        }
+    }
+
+    /// The signature is whether it's valid.
+    type SignedLedger = Ledger<char, u64, (), bool>;
+
+    #[derive(Debug)]
+    struct CheckBool;
+
+    impl TransferVerifier<char, u64, (), bool> for CheckBool {
+        fn verify(&self, args: TransferVerifierArgs<&char, &u64, &(), &bool>) -> bool {
+            *args.benefactor_signature
+        }
+        fn verify_mint(&self, args: MintVerifierArgs<&char, &u64, &(), &bool>) -> bool {
+            *args.authority_signature
+        }
+    }
+
+    #[test]
+    fn mint_authorities() {
+        let mint = |authority_signature| LedgerEvent::Mint {
+            beneficiary: 'a',
+            amount: 1,
+            authority_signature,
+        };
+        let open = SignedLedger::genesis_signed([('a', ())], []).unwrap();
+        assert!(open.with_event(mint(None)).is_ok());
+
+        let ledger = open.with_verifier(CheckBool).with_mint_authorities([()]);
+        assert!(ledger.with_event(mint(Some(true))).is_ok());
+        for bad in [None, Some(false)] {
+            assert!(matches!(
+                ledger.with_event(mint(bad)),
+                Err(AcceptEventError::UnauthorizedMint)
+            ));
+            assert!(matches!(
+                ledger.with_events(&[mint(Some(true)), mint(bad)], NonZeroUsize::MIN),
+                Err((1, AcceptEventError::UnauthorizedMint))
+            ));
+            assert!(matches!(
+                ledger.clone().import_events([mint(bad)]),
+                Err((0, AcceptEventError::UnauthorizedMint))
+            ));
+        }
+        // the authorities are kept as the history grows
+        let ledger = ledger.with_event(mint(Some(true))).unwrap();
+        assert!(ledger.with_event(mint(None)).is_err());
+        assert_eq!(ledger.users()[&'a'].balance, 1);
+        // and each signed mint moves the nonce on
+        assert_eq!(ledger.next_mint_nonce(&'a'), Some(1));
+        let mut undone = ledger.clone();
+        undone.undo_events(1);
+        assert_eq!(undone.next_mint_nonce(&'a'), Some(0));
+    }
+
+    #[test]
+    fn block_rewards() {
+        let mint = |amount, authority_signature| LedgerEvent::Mint {
+            beneficiary: 'a',
+            amount,
+            authority_signature,
+        };
+        let mut ledger = SignedLedger::genesis_signed([('a', ())], [])
+            .unwrap()
+            .with_verifier(CheckBool)
+            .with_mint_authorities([()])
+            .with_block_reward(2);
+        // only blocks have a reward
+        assert!(matches!(
+            ledger.extend_events(&[mint(2, None)], NonZeroUsize::MIN),
+            Err((0, AcceptEventError::UnauthorizedMint))
+        ));
+        for (bad, event_index) in [
+            (vec![mint(3, None)], 0),
+            (vec![mint(1, None), mint(1, None)], 1),
+            (vec![mint(1, Some(false)), mint(1, None)], 0),
+        ] {
+            assert!(matches!(
+                ledger.extend_block(&bad, NonZeroUsize::MIN),
+                Err((index, AcceptEventError::UnauthorizedMint)) if index == event_index
+            ));
+        }
+        assert_eq!(ledger.users()[&'a'].balance, 0);
+
+        ledger
+            .extend_block(&[mint(5, Some(true)), mint(2, None)], NonZeroUsize::MIN)
+            .unwrap();
+        ledger
+            .extend_block(&[mint(1, None)], NonZeroUsize::MIN)
+            .unwrap();
+        assert_eq!(ledger.users()[&'a'].balance, 8);
+        // the reward isn't signed, so doesn't take a nonce
+        assert_eq!(ledger.next_mint_nonce(&'a'), Some(1));
+    }
+
+    #[test]
+    fn with_events_finds_the_first_bad_signature() {
+        let mut events = vec![
+            LedgerEvent::NewUser {
+                identifier: 'a',
+                public_key: (),
+            },
+            LedgerEvent::NewUser {
+                identifier: 'b',
+                public_key: (),
+            },
+            LedgerEvent::Mint {
+                beneficiary: 'a',
+                amount: 1000,
+                authority_signature: None,
+            },
+        ];
+        events.extend((0..500).map(|nonce| LedgerEvent::Transfer {
+            benefactor: 'a',
+            beneficiary: 'b',
+            amount: 1,
+            nonce,
+            benefactor_signature: true,
+        }));
+        for num_threads in [1, 2, 7] {
+            let num_threads = NonZeroUsize::new(num_threads).unwrap();
+            let ledger = SignedLedger::default()
+                .with_verifier(CheckBool)
+                .with_events(&events, num_threads)
+                .unwrap();
+            assert_eq!(ledger.users()[&'b'].balance, 500);
+
+            let mut bad = events.clone();
+            for index in [400, 123] {
+                bad[index] = LedgerEvent::Transfer {
+                    benefactor: 'a',
+                    beneficiary: 'b',
+                    amount: 1,
+                    nonce: index as u64 - 3,
+                    benefactor_signature: false,
+                };
+            }
+            assert!(matches!(
+                SignedLedger::default()
+                    .with_verifier(CheckBool)
+                    .with_events(&bad, num_threads),
+                Err((123, AcceptEventError::InvalidSignature))
+            ));
+        }
+    }
+
+    #[test]
+    fn changing_the_verifier_only_affects_later_events() {
+        let forged = |amount, nonce| LedgerEvent::Transfer {
+            benefactor: 'a',
+            beneficiary: 'b',
+            amount,
+            nonce,
+            benefactor_signature: false,
+        };
+        let ledger = SignedLedger::genesis_signed([('a', ()), ('b', ())], [('a', 10)])
+            .unwrap()
+            .with_event(forged(1, 0))
+            .unwrap()
+            .with_verifier(CheckBool);
+        assert_eq!(ledger.users()[&'b'].balance, 1);
+        assert!(matches!(
+            ledger.with_event(forged(2, 1)),
+            Err(AcceptEventError::InvalidSignature)
+        ));
+        let ledger = ledger
+            .with_event(LedgerEvent::Transfer {
+                benefactor: 'a',
+                beneficiary: 'b',
+                amount: 2,
+                nonce: 1,
+                benefactor_signature: true,
+            })
+            .unwrap();
+        assert_eq!(ledger.users()[&'b'].balance, 3);
+        assert!(matches!(
+            ledger.with_events(&[forged(3, 2)], NonZeroUsize::MIN),
+            Err((0, AcceptEventError::InvalidSignature))
+        ));
+        // and back again
+        assert!(ledger
+            .with_verifier(AlwaysAccept)
+            .with_event(forged(3, 2))
+            .is_ok());
+    }
+
+    #[test]
+    fn import_events_checks_signatures_first() {
+        let ledger = SignedLedger::genesis_signed([('a', ()), ('b', ())], [('a', 10)])
+            .unwrap()
+            .with_verifier(CheckBool);
+        let transfer = |amount, nonce, benefactor_signature| LedgerEvent::Transfer {
+            benefactor: 'a',
+            beneficiary: 'b',
+            amount,
+            nonce,
+            benefactor_signature,
+        };
+        assert!(matches!(
+            ledger.with_event(transfer(100, 0, false)),
+            Err(AcceptEventError::InvalidSignature)
+        ));
+        assert!(matches!(
+            ledger
+                .clone()
+                .import_events([transfer(1, 0, true), transfer(100, 1, false)]),
+            Err((1, AcceptEventError::InvalidSignature))
+        ));
+        assert!(matches!(
+            ledger
+                .clone()
+                .import_events([transfer(1, 0, true), transfer(100, 1, true)]),
+            Err((1, AcceptEventError::WouldOverdraw))
+        ));
+        let imported = ledger.import_events([transfer(1, 0, true)]).unwrap();
+        assert_eq!(imported.users()[&'b'].balance, 1);
+    }
+
+    #[test]
+    fn capacity_is_reserved() {
+        assert!(
+            TestLedger::default()
+                .with_event_capacity(100)
+                .events
+                .capacity()
+                >= 100
+        );
+
+        let blocks = [
+            Block {
+                parent: None,
+                id: 0,
+                events: vec![new_user('a'), new_user('b'), mint('a', 10)],
+            },
+            Block {
+                parent: Some(0),
+                id: 1,
+                events: vec![transfer('a', 'b', 3, 0), transfer('b', 'a', 1, 0)],
+            },
+        ];
+        let from_blocks = TestLedger::from_blocks(&blocks).unwrap();
+        assert!(from_blocks.events.capacity() >= 5);
+        let one_by_one = ledger([
+            new_user('a'),
+            new_user('b'),
+            mint('a', 10),
+            transfer('a', 'b', 3, 0),
+            transfer('b', 'a', 1, 0),
+        ]);
+        assert_eq!(from_blocks.events, one_by_one.events);
+        assert_eq!(from_blocks.users(), one_by_one.users());
+
+        let mut bad = blocks;
+        bad[1].events.push(transfer('b', 'a', 100, 1));
+        assert!(matches!(
+            TestLedger::from_blocks(&bad),
+            Err((1, 2, AcceptEventError::WouldOverdraw))
+        ));
+    }
+
+    #[test]
+    fn strict_signatures() {
+        let accounts = [('a', ()), ('b', ())];
+        let strict = SignedLedger::genesis_signed(accounts, [('a', 10)])
+            .unwrap()
+            .with_strict_signatures(true);
+        let transfer = LedgerEvent::Transfer {
+            benefactor: 'a',
+            beneficiary: 'b',
+            amount: 1,
+            nonce: 0,
+            benefactor_signature: true,
+        };
+        assert!(matches!(
+            strict.with_event(transfer),
+            Err(AcceptEventError::VerifierNotConfigured)
+        ));
+        assert!(matches!(
+            strict.with_events(
+                &[
+                    LedgerEvent::Mint {
+                        beneficiary: 'b',
+                        amount: 1,
+                        authority_signature: None,
+                    },
+                    transfer
+                ],
+                NonZeroUsize::MIN
+            ),
+            Err((1, AcceptEventError::VerifierNotConfigured))
+        ));
+        assert!(matches!(
+            strict.clone().import_events([transfer]),
+            Err((0, AcceptEventError::VerifierNotConfigured))
+        ));
+        // everything else is fine
+        assert!(strict
+            .with_event(LedgerEvent::Mint {
+                beneficiary: 'b',
+                amount: 1,
+                authority_signature: None,
+            })
+            .is_ok());
+
+        let strict = strict.with_verifier(CheckBool);
+        assert!(strict.with_event(transfer).is_ok());
+        assert!(strict.with_events(&[transfer], NonZeroUsize::MIN).is_ok());
+        assert!(strict.import_events([transfer]).is_ok());
+    }
+}
@@ -3,65 +3,61 @@
 // But how does bitcoin actually work? - 3Blue1Brown
 // https://www.youtube.com/watch?v=bBC-nXj3Ng4
 
-mod blockchain;
-mod ledger;
-mod proof_of_work;
-
-pub use blockchain::{AddBlockError, AddBlockOk, Block, BlockGraph};
-pub use ledger::{AcceptEventError, Ledger, LedgerEvent, TransferVerifierArgs, UserSummary};
-pub use proof_of_work::{check_work, do_work, DoWorkError, WithProofOfWork};
-
-type PublicKey = p256::ecdsa::VerifyingKey;
-type UserId = PublicKey;
-type Signature = p256::ecdsa::Signature;
-type BlockId = sha2::digest::Output<sha2::Sha256>;
-
-// Optimisation ideas:
-// - Keep ledger progress in the block graph, compacting every N blocks
-// - We can optimise our graph because it's immutable - maybe just allocate blocks in an arena
-// - We can tune our optimisation based on our correctness tolerance
-pub struct ValidatorNode {
-    ledger: Ledger<UserId, u64, PublicKey, Signature>,
-    blocks: BlockGraph<BlockId, UserId, u64, PublicKey, Signature>,
-}
-
-impl ValidatorNode {
-    pub fn ingest_block(
-        &mut self,
-        block: WithProofOfWork<Block<BlockId, UserId, u64, PublicKey, Signature>>,
-    ) -> Result<(), BlockIngestError> {
-        // Does this count as easily precomputable? Probably...
-        let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(block.inner.id);
-        proof_of_work::check_work(c, re_min, re_max, block.candidate, target_iterations)
-            .map_err(BlockIngestError::DoWorkError)?;
-
-        let block = block.inner;
-
-        match self.blocks.add_block(block) {
-            Ok(AddBlockOk::CanAddNewEventsToLedger) => {
-                // TODO(newtype so we can hash the key)
-                // self.ledger = self.ledger.with_event(todo!());
-                todo!()
-            }
-            Ok(AddBlockOk::MustRebuildCache) => {
-                let _winning_chain = self.blocks.winning_chain();
-                let ledger = todo!("fold ledger");
-                // there's an error condition here we need to handle - invalid events in a block that passed pow
-            }
-            Ok(AddBlockOk::Noop) => {}
-            Err(AddBlockError::WouldClobber) => unreachable!("hash collision"),
-        }
-
-        Ok(())
-    }
+// Only checking work is available without std, see the `julia` module
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Time part of validation with a [tracing] span, if the `validation-spans` feature is on.
+///
+/// Otherwise this is a no-op, and the fields aren't evaluated.
+#[cfg(feature = "std")]
+macro_rules! validation_span {
+    ($name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "validation-spans")]
+        let span = tracing::info_span!($name $(, $($fields)*)?).entered();
+        #[cfg(not(feature = "validation-spans"))]
+        let span = $crate::NoSpan;
+        span
+    }};
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum BlockIngestError {
-    #[error("block's work was invalid")]
-    DoWorkError(proof_of_work::DoWorkError),
-}
+/// What [validation_span] gives without the `validation-spans` feature.
+#[cfg(all(feature = "std", not(feature = "validation-spans")))]
+struct NoSpan;
 
-fn get_work_params_from_block_id(id: BlockId) -> (num::Complex<f64>, f64, f64, u16) {
-    todo!()
-}
+#[cfg(feature = "std")]
+mod blockchain;
+mod julia;
+#[cfg(feature = "std")]
+mod ledger;
+#[cfg(feature = "std")]
+mod merkle;
+#[cfg(feature = "std")]
+mod node;
+#[cfg(feature = "std")]
+mod proof_of_work;
+#[cfg(feature = "std")]
+mod signature_scheme;
+#[cfg(all(feature = "std", any(test, feature = "testing")))]
+pub mod testing;
+
+#[cfg(feature = "std")]
+pub use blockchain::{
+    common_prefix, AddBlockError, AddBlockOk, Block, BlockGraph, Chain, ChainRow,
+};
+pub use julia::{check_work, DoWorkError, DEFAULT_RE_MAX, DEFAULT_RE_MIN};
+#[cfg(feature = "std")]
+pub use ledger::{
+    AcceptEventError, AlwaysAccept, EventCounts, Ledger, LedgerEvent, LedgerSnapshot,
+    MintVerifierArgs, TransferVerifier, TransferVerifierArgs, UserSummary,
+};
+#[cfg(feature = "std")]
+pub use node::*;
+#[cfg(feature = "std")]
+pub use proof_of_work::{
+    candidate_difficulty, check_work_multi, default_chunk_size, difficulty_surface, do_work,
+    do_work_cancellable, do_work_multi, do_work_near, do_work_parallel, do_work_with_backoff,
+    expected_attempts, julia_orbit, max_solvable_iterations, MultiWorkError, SearchRegion,
+    WithProofOfWork, WithProofsOfWork, MAX_WIDENINGS,
+};
+#[cfg(feature = "std")]
+pub use signature_scheme::{SignatureScheme, P256};
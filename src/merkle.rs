@@ -0,0 +1,86 @@
+// A plain binary merkle tree over sha256.
+//
+// When a level has an odd number of nodes, the last one is paired with itself,
+// as in bitcoin.
+// Leaves and interior nodes are hashed with different prefixes, so a leaf can't
+// be passed off as an interior node.
+
+use sha2::Digest as _;
+
+type Hash = sha2::digest::Output<sha2::Sha256>;
+
+pub fn hash_leaf(data: &[u8]) -> Hash {
+    sha2::Sha256::new()
+        .chain_update([0])
+        .chain_update(data)
+        .finalize()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    sha2::Sha256::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+}
+
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [odd] => hash_node(odd, odd),
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// The root of the tree with these leaves.
+/// An empty tree has the hash of no data as its root.
+pub fn root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return sha2::Sha256::digest([]);
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// The siblings on the path from the leaf at `index` to the root, starting nearest the leaf.
+///
+/// Returns [None] if `index` is out of bounds.
+pub fn proof(leaves: &[Hash], index: usize) -> Option<Vec<Hash>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let (mut level, mut index, mut proof) = (leaves.to_vec(), index, vec![]);
+    while level.len() > 1 {
+        let sibling = match index % 2 {
+            0 => level.get(index + 1).unwrap_or(&level[index]), // odd one out is its own sibling
+            _ => &level[index - 1],
+        };
+        proof.push(*sibling);
+        level = next_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Whether `leaf` is at `index` in the tree with the given `root`, according to `proof`.
+///
+/// Because of the odd-leaf rule, the last leaf of a level with an odd number of nodes
+/// also verifies at the (nonexistent) index after it.
+pub fn verify(root: Hash, leaf: Hash, index: usize, proof: &[Hash]) -> bool {
+    let (mut current, mut index) = (leaf, index);
+    for sibling in proof {
+        current = match index % 2 {
+            0 => hash_node(&current, sibling),
+            _ => hash_node(sibling, &current),
+        };
+        index /= 2;
+    }
+    // an index beyond the width of the tree doesn't describe a path
+    index == 0 && current == root
+}
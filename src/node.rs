@@ -0,0 +1,2627 @@
+//! Users, blocks, and the [ValidatorNode] which keeps track of them.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    candidate_difficulty, common_prefix, merkle, AcceptEventError, AddBlockError, AddBlockOk,
+    AlwaysAccept, Block, BlockGraph, DoWorkError, Ledger, LedgerEvent, MintVerifierArgs,
+    SignatureScheme, TransferVerifier, TransferVerifierArgs, WithProofOfWork, DEFAULT_RE_MAX,
+    DEFAULT_RE_MIN, P256,
+};
+use itertools::Itertools as _;
+use sha2::Digest as _;
+
+pub(crate) type PublicKey<S = P256> = <S as SignatureScheme>::PublicKey;
+pub(crate) type Signature<S = P256> = <S as SignatureScheme>::Signature;
+pub(crate) type BlockId = sha2::digest::Output<sha2::Sha256>;
+pub(crate) type Event<S = P256> = LedgerEvent<UserId<S>, u64, PublicKey<S>, Signature<S>>;
+pub(crate) type NodeBlock<S = P256> = Block<BlockId, UserId<S>, u64, PublicKey<S>, Signature<S>>;
+pub(crate) type NodeGraph<S = P256> =
+    BlockGraph<BlockId, UserId<S>, u64, PublicKey<S>, Signature<S>>;
+pub(crate) type NodeLedger<S = P256> = Ledger<UserId<S>, u64, PublicKey<S>, Signature<S>>;
+
+/// Users are identified by their public key.
+///
+/// [SignatureScheme::PublicKey] needn't implement [Hash], so we can't use it as a key in the
+/// [Ledger] directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UserId<S: SignatureScheme = P256>(pub PublicKey<S>);
+
+impl<S: SignatureScheme> Hash for UserId<S> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        S::public_key_bytes(&self.0).as_ref().hash(state)
+    }
+}
+
+/// The message that a benefactor signs to authorise a [LedgerEvent::Transfer].
+///
+/// The nonce is included, so the signature can't be reused for a later transfer,
+/// see [crate::UserSummary::next_nonce].
+pub fn transfer_message<S: SignatureScheme>(
+    benefactor: &UserId<S>,
+    beneficiary: &UserId<S>,
+    amount: u64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = b"transfer".to_vec();
+    message.extend_from_slice(S::public_key_bytes(&benefactor.0).as_ref());
+    message.extend_from_slice(S::public_key_bytes(&beneficiary.0).as_ref());
+    message.extend_from_slice(&amount.to_be_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
+
+/// The message that a minting authority signs to authorise a [LedgerEvent::Mint],
+/// see [ValidatorNode::with_mint_authorities].
+///
+/// `nonce` is the beneficiary's [crate::UserSummary::next_mint_nonce], so a signed mint can't be
+/// replayed.
+pub fn mint_message<S: SignatureScheme>(
+    beneficiary: &UserId<S>,
+    amount: u64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = b"mint".to_vec();
+    message.extend_from_slice(S::public_key_bytes(&beneficiary.0).as_ref());
+    message.extend_from_slice(&amount.to_be_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
+
+/// The id of a block is the hash of its parent and the [events_merkle_root] of its events.
+pub fn hash_block<S: SignatureScheme>(parent: Option<BlockId>, events: &[Event<S>]) -> BlockId {
+    let mut hasher = sha2::Sha256::new();
+    match parent {
+        Some(parent) => {
+            hasher.update([1]);
+            hasher.update(parent);
+        }
+        None => hasher.update([0]),
+    }
+    hasher.update(events_merkle_root(events));
+    hasher.finalize()
+}
+
+fn hash_event<S: SignatureScheme>(event: &Event<S>) -> BlockId {
+    let mut data = vec![];
+    match event {
+        LedgerEvent::NewUser {
+            identifier,
+            public_key,
+        } => {
+            data.extend_from_slice(b"new-user");
+            data.extend_from_slice(S::public_key_bytes(&identifier.0).as_ref());
+            data.extend_from_slice(S::public_key_bytes(public_key).as_ref());
+        }
+        LedgerEvent::Mint {
+            beneficiary,
+            amount,
+            authority_signature,
+        } => {
+            data.extend_from_slice(b"mint");
+            data.extend_from_slice(S::public_key_bytes(&beneficiary.0).as_ref());
+            data.extend_from_slice(&amount.to_be_bytes());
+            match authority_signature {
+                Some(signature) => {
+                    data.push(1);
+                    data.extend_from_slice(S::signature_bytes(signature).as_ref());
+                }
+                None => data.push(0),
+            }
+        }
+        LedgerEvent::Transfer {
+            benefactor,
+            beneficiary,
+            amount,
+            nonce,
+            benefactor_signature,
+        } => {
+            data.extend_from_slice(b"transfer");
+            data.extend_from_slice(S::public_key_bytes(&benefactor.0).as_ref());
+            data.extend_from_slice(S::public_key_bytes(&beneficiary.0).as_ref());
+            data.extend_from_slice(&amount.to_be_bytes());
+            data.extend_from_slice(&nonce.to_be_bytes());
+            data.extend_from_slice(S::signature_bytes(benefactor_signature).as_ref());
+        }
+    }
+    merkle::hash_leaf(&data)
+}
+
+/// The root of a merkle tree over these events.
+///
+/// When a level of the tree has an odd number of nodes, the last node is paired with itself.
+pub fn events_merkle_root<S: SignatureScheme>(events: &[Event<S>]) -> BlockId {
+    merkle::root(&events.iter().map(hash_event).collect::<Vec<_>>())
+}
+
+/// A proof that the event at `index` is included under [events_merkle_root],
+/// for [verify_event_inclusion].
+///
+/// Returns [None] if `index` is out of bounds.
+pub fn event_inclusion_proof<S: SignatureScheme>(
+    events: &[Event<S>],
+    index: usize,
+) -> Option<Vec<BlockId>> {
+    merkle::proof(&events.iter().map(hash_event).collect::<Vec<_>>(), index)
+}
+
+/// Check a proof from [event_inclusion_proof], without needing the rest of the events.
+///
+/// Note that the last event also verifies at `index + 1` if it was the odd one out
+/// at the bottom of the tree.
+pub fn verify_event_inclusion<S: SignatureScheme>(
+    root: BlockId,
+    event: &Event<S>,
+    index: usize,
+    proof: &[BlockId],
+) -> bool {
+    merkle::verify(root, hash_event(event), index, proof)
+}
+
+/// Check that each block's id is the hash of its contents, see [hash_block].
+///
+/// Returns the index of the first block whose id doesn't match.
+pub fn validate_block_ids<S: SignatureScheme>(blocks: &[NodeBlock<S>]) -> Result<(), usize> {
+    match blocks
+        .iter()
+        .position(|block| block.id != hash_block(block.parent, &block.events))
+    {
+        Some(index) => Err(index),
+        None => Ok(()),
+    }
+}
+
+/// Fully validate a chain, starting from a genesis block, returning the resulting ledger.
+///
+/// Each block must have a valid id, valid work, and valid events,
+/// and have the previous block as its parent.
+pub fn verify_chain<S: SignatureScheme>(
+    blocks: &[WithProofOfWork<NodeBlock<S>>],
+) -> Result<NodeLedger<S>, ChainVerifyError> {
+    verify_chain_bounded(blocks, u64::MAX)
+}
+
+/// Like [verify_chain], but gives up once checking the work would take more than
+/// `max_work_iterations` iterations of the julia map in total.
+///
+/// Each block is charged the most its work could take to check (`target_iterations + 1`)
+/// before it is checked, so the budget is never overrun.
+pub fn verify_chain_bounded<S: SignatureScheme>(
+    blocks: &[WithProofOfWork<NodeBlock<S>>],
+    max_work_iterations: u64,
+) -> Result<NodeLedger<S>, ChainVerifyError> {
+    verify_chain_configured(blocks, max_work_iterations, |ledger| {
+        ledger.with_verifier(SchemeVerifier::<S>::default())
+    })
+}
+
+/// Like [verify_chain_bounded], but checking events with a ledger set up by `configure`,
+/// e.g with [ValidatorNode::configure].
+fn verify_chain_configured<S: SignatureScheme>(
+    blocks: &[WithProofOfWork<NodeBlock<S>>],
+    max_work_iterations: u64,
+    configure: impl Fn(NodeLedger<S>) -> NodeLedger<S>,
+) -> Result<NodeLedger<S>, ChainVerifyError> {
+    let mut ledger = configure(Ledger::default());
+    let mut work_iterations = 0u64;
+    for (index, block) in blocks.iter().enumerate() {
+        validate_block_ids(std::slice::from_ref(&block.inner))
+            .map_err(|_| ChainVerifyError::IdMismatch { index })?;
+        match (
+            index.checked_sub(1).map(|it| blocks[it].inner.id),
+            block.inner.parent,
+        ) {
+            (None, None) => {}
+            (Some(_), None) => return Err(ChainVerifyError::UnexpectedGenesis { index }),
+            (Some(previous), Some(parent)) if previous == parent => {}
+            (_, Some(_)) => return Err(ChainVerifyError::BadParentLink { index }),
+        }
+        let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(block.inner.id);
+        work_iterations = work_iterations.saturating_add(u64::from(target_iterations) + 1);
+        if work_iterations > max_work_iterations {
+            return Err(ChainVerifyError::WorkBudgetExceeded { index });
+        }
+        crate::check_work(c, re_min, re_max, block.candidate, target_iterations)
+            .map_err(|source| ChainVerifyError::BadWork { index, source })?;
+        ledger = apply_block(ledger, &block.inner, NonZeroUsize::MIN).map_err(
+            |(event_index, source)| ChainVerifyError::BadEvent {
+                block_index: index,
+                event_index,
+                source,
+            },
+        )?;
+    }
+    Ok(ledger)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChainVerifyError {
+    #[error("block {index} has invalid work")]
+    BadWork {
+        index: usize,
+        #[source]
+        source: DoWorkError,
+    },
+    #[error("block {index} doesn't have the previous block as its parent")]
+    BadParentLink { index: usize },
+    #[error("block {index}'s id doesn't match its contents")]
+    IdMismatch { index: usize },
+    #[error("event {event_index} in block {block_index} is invalid")]
+    BadEvent {
+        block_index: usize,
+        event_index: usize,
+        #[source]
+        source: AcceptEventError,
+    },
+    #[error("block {index} is a genesis block, but isn't at the start of the chain")]
+    UnexpectedGenesis { index: usize },
+    #[error("checking the work of block {index} would exceed the budget")]
+    WorkBudgetExceeded { index: usize },
+}
+
+/// The state of the winning chain as of a particular block.
+#[derive(Debug, Clone)]
+pub struct Checkpoint<S: SignatureScheme = P256> {
+    pub block_id: BlockId,
+    pub ledger: NodeLedger<S>,
+}
+
+/// A change of winning chain that discarded blocks from the previous winning chain.
+///
+/// Heights count from the root of the chain, which has height 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReorgRecord {
+    pub old_height: usize,
+    pub old_tip: BlockId,
+    pub new_height: usize,
+    pub new_tip: BlockId,
+    /// How many blocks from the previous winning chain are no longer on it.
+    pub rolled_back: usize,
+}
+
+struct MempoolEntry<S: SignatureScheme> {
+    submitted: Instant,
+    event: Event<S>,
+}
+
+/// A block that hasn't been mined yet, see [ValidatorNode::block_template].
+///
+/// It doesn't borrow the node, so can be mined on another thread while the node carries on
+/// ingesting blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTemplate<S: SignatureScheme = P256> {
+    pub parent: Option<BlockId>,
+    pub events: Vec<Event<S>>,
+}
+
+impl<S: SignatureScheme> BlockTemplate<S> {
+    /// Do this block's work, giving up once `stop` is set.
+    ///
+    /// Work on the block is wasted once the tip moves, so callers should set `stop` when
+    /// [ValidatorNode::ingest_block] changes the winning chain, and then mine a new template.
+    pub fn mine_until_tip_changes(
+        self,
+        stop: &AtomicBool,
+    ) -> Option<WithProofOfWork<NodeBlock<S>>> {
+        self.mine_with(|c, re_min, re_max, target_iterations| {
+            crate::do_work_cancellable(
+                c,
+                re_min,
+                re_max,
+                target_iterations,
+                crate::default_chunk_size(target_iterations),
+                stop,
+            )
+        })
+    }
+
+    fn mine_with(
+        self,
+        do_work: impl FnOnce(num::Complex<f64>, f64, f64, u16) -> Option<num::Complex<f64>>,
+    ) -> Option<WithProofOfWork<NodeBlock<S>>> {
+        let Self { parent, events } = self;
+        let id = hash_block(parent, &events);
+        let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(id);
+        Some(WithProofOfWork {
+            candidate: do_work(c, re_min, re_max, target_iterations)?,
+            inner: Block { parent, id, events },
+        })
+    }
+}
+
+/// How many [ReorgRecord]s a [ValidatorNode] remembers by default.
+const DEFAULT_RECENT_REORGS_CAPACITY: usize = 16;
+
+/// How many orphan blocks a [ValidatorNode] holds on to by default.
+const DEFAULT_MAX_ORPHANS: usize = 1024;
+
+/// How many refused blocks a [ValidatorNode] remembers, so that it can refuse their
+/// descendants too.
+const REFUSED_CAPACITY: usize = 1024;
+
+// Optimisation ideas:
+// - Keep ledger progress in the block graph, compacting every N blocks
+// - We can optimise our graph because it's immutable - maybe just allocate blocks in an arena
+// - We can tune our optimisation based on our correctness tolerance
+pub struct ValidatorNode<S: SignatureScheme = P256> {
+    ledger: NodeLedger<S>,
+    blocks: NodeGraph<S>,
+    /// If we were bootstrapped from a [Checkpoint], the root of [Self::blocks] is the
+    /// checkpoint block, and all of history is summarised by this ledger.
+    checkpoint: Option<Checkpoint<S>>,
+    /// Oldest first.
+    recent_reorgs: VecDeque<ReorgRecord>,
+    recent_reorgs_capacity: usize,
+    /// See [Self::with_max_reorg_depth].
+    max_reorg_depth: usize,
+    /// See [Self::with_validation_threads].
+    validation_threads: NonZeroUsize,
+    /// See [Self::set_verifier].
+    verifier: Arc<dyn TransferVerifier<UserId<S>, u64, PublicKey<S>, Signature<S>>>,
+    /// See [Self::with_strict_signatures].
+    strict_signatures: bool,
+    /// See [Self::with_reject_empty_blocks].
+    reject_empty_blocks: bool,
+    /// See [Self::with_mint_authorities].
+    mint_authorities: Vec<PublicKey<S>>,
+    /// See [Self::with_block_reward].
+    block_reward: Option<u64>,
+    /// Events waiting to be mined, in submission order.
+    mempool: Vec<MempoolEntry<S>>,
+    /// Blocks with valid work whose parent we haven't seen yet, keyed by that parent,
+    /// and then by their own id.
+    orphans: HashMap<BlockId, HashMap<BlockId, NodeBlock<S>>>,
+    /// See [Self::with_max_orphans].
+    max_orphans: usize,
+    /// Blocks which couldn't be connected, oldest first, see [BlockIngestError::ParentRefused].
+    refused: VecDeque<BlockId>,
+}
+
+impl<S: SignatureScheme> Default for ValidatorNode<S> {
+    fn default() -> Self {
+        let verifier = Arc::new(SchemeVerifier::<S>::default());
+        Self {
+            ledger: Ledger::default().with_verifier(verifier.clone()),
+            blocks: BlockGraph::default(),
+            checkpoint: None,
+            recent_reorgs: VecDeque::with_capacity(DEFAULT_RECENT_REORGS_CAPACITY),
+            recent_reorgs_capacity: DEFAULT_RECENT_REORGS_CAPACITY,
+            max_reorg_depth: usize::MAX,
+            validation_threads: NonZeroUsize::MIN,
+            verifier,
+            strict_signatures: false,
+            reject_empty_blocks: false,
+            mint_authorities: Vec::new(),
+            block_reward: None,
+            mempool: Vec::new(),
+            orphans: HashMap::new(),
+            max_orphans: DEFAULT_MAX_ORPHANS,
+            refused: VecDeque::with_capacity(REFUSED_CAPACITY),
+        }
+    }
+}
+
+impl ValidatorNode {
+    /// A node using [P256], see [Self::default] for other schemes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: SignatureScheme> ValidatorNode<S> {
+    /// Remember at most `capacity` reorgs in [Self::recent_reorgs].
+    pub fn with_recent_reorgs_capacity(mut self, capacity: usize) -> Self {
+        self.recent_reorgs_capacity = capacity;
+        while self.recent_reorgs.len() > capacity {
+            self.recent_reorgs.pop_front();
+        }
+        self
+    }
+
+    /// Refuse blocks which fork off the winning chain more than `depth` blocks below the tip,
+    /// with [BlockIngestError::ReorgTooDeep], even if that fork would go on to win.
+    ///
+    /// By default, any fork is accepted.
+    pub fn with_max_reorg_depth(mut self, depth: usize) -> Self {
+        self.max_reorg_depth = depth;
+        self
+    }
+
+    /// Hold on to at most `max` blocks whose parent hasn't arrived yet, refusing any more with
+    /// [BlockIngestError::TooManyOrphans].
+    ///
+    /// Orphans already held are kept, even if there are more than `max` of them.
+    /// By default, up to 1024 orphans are held.
+    pub fn with_max_orphans(mut self, max: usize) -> Self {
+        self.max_orphans = max;
+        self
+    }
+
+    /// Refuse blocks other than genesis which have no events, with
+    /// [BlockIngestError::EmptyBlock].
+    ///
+    /// Off by default.
+    pub fn with_reject_empty_blocks(mut self, reject: bool) -> Self {
+        self.reject_empty_blocks = reject;
+        self
+    }
+
+    /// Refuse mints which aren't signed by one of `authorities` (see [mint_message]), with
+    /// [AcceptEventError::UnauthorizedMint], see [Ledger::with_mint_authorities].
+    ///
+    /// Only the block reward needn't be signed, see [Self::with_block_reward].
+    ///
+    /// By default, there are no authorities, and any mint is accepted.
+    pub fn with_mint_authorities(
+        mut self,
+        authorities: impl IntoIterator<Item = PublicKey<S>>,
+    ) -> Self {
+        self.mint_authorities = authorities.into_iter().collect();
+        let ledger = std::mem::take(&mut self.ledger);
+        self.ledger = self.configure(ledger);
+        self
+    }
+
+    /// Let each block, including genesis, carry one unsigned mint of at most `max`, despite
+    /// the [Self::with_mint_authorities], see [Ledger::with_block_reward].
+    ///
+    /// By default, blocks have no reward.
+    pub fn with_block_reward(mut self, max: u64) -> Self {
+        self.block_reward = Some(max);
+        let ledger = std::mem::take(&mut self.ledger);
+        self.ledger = self.configure(ledger);
+        self
+    }
+
+    /// Check a block's signatures on `num_threads` threads, see [Ledger::with_events].
+    ///
+    /// By default, blocks are validated on the calling thread.
+    pub fn with_validation_threads(mut self, num_threads: NonZeroUsize) -> Self {
+        self.validation_threads = num_threads;
+        self
+    }
+
+    /// Check the signatures of transfers in new blocks and the mempool with `verifier`.
+    ///
+    /// Blocks this node has already accepted stay accepted, even if `verifier` would
+    /// reject them.
+    /// By default, transfers are checked with [P256TransferVerifier].
+    pub fn set_verifier(
+        &mut self,
+        verifier: impl TransferVerifier<UserId<S>, u64, PublicKey<S>, Signature<S>> + 'static,
+    ) {
+        self.verifier = Arc::new(verifier);
+        let ledger = std::mem::take(&mut self.ledger);
+        self.ledger = self.configure(ledger);
+    }
+
+    /// Refuse all transfers while the verifier is [AlwaysAccept], see
+    /// [Ledger::with_strict_signatures].
+    ///
+    /// Off by default.
+    pub fn with_strict_signatures(mut self, strict: bool) -> Self {
+        self.strict_signatures = strict;
+        let ledger = std::mem::take(&mut self.ledger);
+        self.ledger = self.configure(ledger);
+        self
+    }
+
+    /// `ledger`, checking new events as this node does.
+    fn configure(&self, ledger: NodeLedger<S>) -> NodeLedger<S> {
+        let ledger = ledger
+            .with_verifier(self.verifier.clone())
+            .with_strict_signatures(self.strict_signatures)
+            .with_mint_authorities(self.mint_authorities.clone());
+        match self.block_reward {
+            Some(max) => ledger.with_block_reward(max),
+            None => ledger,
+        }
+    }
+
+    /// The most recent reorgs this node has gone through, oldest first.
+    ///
+    /// Older reorgs are forgotten, see [Self::with_recent_reorgs_capacity].
+    pub fn recent_reorgs(&self) -> &VecDeque<ReorgRecord> {
+        &self.recent_reorgs
+    }
+
+    /// Start a node from a [Checkpoint], without replaying the history behind it.
+    ///
+    /// # Trust
+    /// The checkpoint's ledger is accepted as-is: none of the proof of work or
+    /// signatures that went into it are checked.
+    /// A forged checkpoint can contain any balances at all, so only load checkpoints
+    /// from a source you trust, like your own node.
+    ///
+    /// Blocks in `blocks_above_checkpoint` are fully validated, as with [Self::ingest_block].
+    /// The checkpoint block itself appears in the [BlockGraph] as a root with no events.
+    pub fn load_trusted(
+        checkpoint: Checkpoint<S>,
+        blocks_above_checkpoint: impl IntoIterator<Item = WithProofOfWork<NodeBlock<S>>>,
+    ) -> Result<Self, BlockIngestError> {
+        let mut blocks = BlockGraph::default();
+        blocks
+            .add_block(Block {
+                parent: None,
+                id: checkpoint.block_id,
+                events: vec![],
+            })
+            .expect("graph is empty");
+        let mut node = Self {
+            blocks,
+            ..Self::default()
+        };
+        node.ledger = node.configure(checkpoint.ledger.clone());
+        node.checkpoint = Some(checkpoint);
+        for block in blocks_above_checkpoint {
+            node.ingest_block(block)?;
+        }
+        Ok(node)
+    }
+
+    /// Replace all of this node's blocks with `blocks`, a whole chain starting from a genesis
+    /// block, if [verify_chain] accepts it.
+    ///
+    /// Events are checked as [Self::ingest_block] would, e.g with this node's verifier and
+    /// mint authorities.
+    /// If the chain isn't accepted, the node is left as it was.
+    /// Otherwise, forks and orphans are forgotten, as is any [Checkpoint] the node was loaded
+    /// from, but the mempool is kept, less any events mined on the new chain.
+    pub fn adopt_chain(
+        &mut self,
+        blocks: Vec<WithProofOfWork<NodeBlock<S>>>,
+    ) -> Result<(), ChainVerifyError> {
+        let ledger = verify_chain_configured(&blocks, u64::MAX, |ledger| self.configure(ledger))?;
+        let mut graph = BlockGraph::default();
+        for block in blocks {
+            let (_, _, _, target_iterations) = get_work_params_from_block_id(block.inner.id);
+            graph
+                .add_block_with_work(block.inner, block_work(target_iterations))
+                .expect("the chain is linked by parents");
+        }
+        let old_chain = std::mem::replace(&mut self.blocks, graph).winning_chain();
+        self.ledger = ledger;
+        self.checkpoint = None;
+        self.orphans.clear();
+        let new_chain = self.blocks.winning_chain();
+        self.record_reorg(&old_chain, &new_chain);
+        self.update_mempool(&old_chain, &new_chain);
+        Ok(())
+    }
+
+    /// The state of the node's winning chain, which may be loaded with [Self::load_trusted].
+    ///
+    /// Returns [None] if the node has no blocks.
+    pub fn checkpoint(&self) -> Option<Checkpoint<S>> {
+        self.blocks
+            .iter_winning_chain()
+            .last()
+            .map(|tip| Checkpoint {
+                block_id: tip.id,
+                ledger: self.ledger.clone(),
+            })
+    }
+
+    /// The ledger according to the winning chain.
+    pub fn ledger(&self) -> &NodeLedger<S> {
+        &self.ledger
+    }
+
+    /// Each account's total incoming (mints and transfers) minus total outgoing (transfers)
+    /// over the winning chain.
+    ///
+    /// Since every account starts empty, this is each account's balance.
+    pub fn net_flows(&self) -> HashMap<UserId<S>, i128> {
+        self.ledger
+            .fold_events(HashMap::new(), |mut flows, _, event| {
+                match event {
+                    LedgerEvent::NewUser { identifier, .. } => {
+                        flows.entry(*identifier).or_insert(0);
+                    }
+                    LedgerEvent::Mint {
+                        beneficiary,
+                        amount,
+                        ..
+                    } => *flows.entry(*beneficiary).or_insert(0) += i128::from(*amount),
+                    LedgerEvent::Transfer {
+                        benefactor,
+                        beneficiary,
+                        amount,
+                        ..
+                    } => {
+                        *flows.entry(*benefactor).or_insert(0) -= i128::from(*amount);
+                        *flows.entry(*beneficiary).or_insert(0) += i128::from(*amount);
+                    }
+                }
+                flows
+            })
+    }
+
+    /// The event at `global_index` when all the events on the winning chain are laid
+    /// end-to-end, and the block it's in.
+    ///
+    /// This is relative to the current winning chain, so indices may refer to different
+    /// events after a reorg.
+    /// For a node started from a [Checkpoint], the checkpoint's history isn't included.
+    pub fn global_event(&self, global_index: usize) -> Option<(BlockId, &Event<S>)> {
+        let mut remaining = global_index;
+        for block in self.blocks.iter_winning_chain() {
+            match remaining.checked_sub(block.events.len()) {
+                Some(rest) => remaining = rest,
+                None => return Some((block.id, &block.events[remaining])),
+            }
+        }
+        None
+    }
+
+    /// How many blocks on the winning chain have an event that affects `user`,
+    /// see [Block::affected_accounts].
+    pub fn active_blocks_of(&self, user: &UserId<S>) -> usize {
+        self.blocks
+            .iter_winning_chain()
+            .filter(|block| block.affected_accounts().contains(user))
+            .count()
+    }
+
+    /// A fingerprint of every account on the winning chain: its id, public key, balance,
+    /// [crate::UserSummary::next_nonce] and [crate::UserSummary::next_mint_nonce].
+    ///
+    /// Nodes which agree on the state of the world have the same state root, however they
+    /// got there, so nodes with different state roots for the same tip have a consensus bug.
+    pub fn state_root(&self) -> BlockId {
+        self.ledger
+            .users()
+            .into_iter()
+            .sorted_by_key(|(id, _)| *id)
+            .fold(sha2::Sha256::new(), |hasher, (id, summary)| {
+                hasher
+                    .chain_update(S::public_key_bytes(&id.0))
+                    .chain_update(S::public_key_bytes(summary.public_key()))
+                    .chain_update(summary.balance().to_be_bytes())
+                    .chain_update(summary.next_nonce().to_be_bytes())
+                    .chain_update(summary.next_mint_nonce().to_be_bytes())
+            })
+            .finalize()
+    }
+
+    /// Check that the node's cached ledger agrees with its blocks, and that no money has
+    /// been created or destroyed other than by mints.
+    pub fn audit(&self) -> Result<(), AuditError> {
+        let users = self.ledger.try_users().map_err(|(event_index, source)| {
+            AuditError::InconsistentLedger {
+                event_index,
+                source,
+            }
+        })?;
+        if self.ledger.users() != users {
+            return Err(AuditError::StaleLedger);
+        }
+        let rebuilt = match self.blocks.iter_winning_chain().last() {
+            Some(tip) => self.ledger_at(&tip.id).expect("tip is in the graph"),
+            None => Ledger::default(),
+        };
+        if rebuilt.try_users().ok().as_ref() != Some(&users) {
+            return Err(AuditError::StaleLedger);
+        }
+        let minted = self.ledger.fold_events(0, |minted, _, event| match event {
+            LedgerEvent::Mint { amount, .. } => minted + u128::from(*amount),
+            _ => minted,
+        });
+        let held = users.values().map(|it| u128::from(*it.balance())).sum();
+        match minted == held {
+            true => Ok(()),
+            false => Err(AuditError::SupplyMismatch { minted, held }),
+        }
+    }
+
+    /// Queue an event to be included in a future block.
+    ///
+    /// The event is checked against the winning chain (ignoring the rest of the mempool),
+    /// and rejected if it could never be accepted there, e.g with
+    /// [AcceptEventError::NoSuchBeneficiary] for a transfer to an unregistered account.
+    /// Events which only fail because of balances ([AcceptEventError::WouldOverdraw] and
+    /// [AcceptEventError::WouldOverflow]) are queued, since balances change, as are transfers
+    /// whose nonce is ahead of the benefactor's, since the transfers before them may be queued.
+    pub fn submit_event(&mut self, event: Event<S>) -> Result<(), AcceptEventError> {
+        self.submit_event_at(event, Instant::now())
+    }
+
+    fn submit_event_at(
+        &mut self,
+        event: Event<S>,
+        submitted: Instant,
+    ) -> Result<(), AcceptEventError> {
+        self.check_queueable(event)?;
+        self.mempool.push(MempoolEntry { submitted, event });
+        Ok(())
+    }
+
+    /// The checks [Self::submit_event] makes before queueing `event`.
+    fn check_queueable(&self, event: Event<S>) -> Result<(), AcceptEventError> {
+        match self.ledger.with_event(event) {
+            Ok(_) | Err(AcceptEventError::WouldOverdraw | AcceptEventError::WouldOverflow) => {
+                Ok(())
+            }
+            Err(AcceptEventError::WrongNonce { expected, found }) if found > expected => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Keep the mempool in step with the winning chain moving from `old_chain` to `new_chain`.
+    ///
+    /// Events mined on the new chain are dropped, and those that were only mined on the
+    /// blocks rolled back are queued again, first, as if they'd just been submitted, unless
+    /// [Self::submit_event] would now refuse them.
+    fn update_mempool(&mut self, old_chain: &[NodeBlock<S>], new_chain: &[NodeBlock<S>]) {
+        let fork = common_prefix(old_chain, new_chain);
+        let mined = new_chain[fork..]
+            .iter()
+            .flat_map(|block| &block.events)
+            .map(hash_event::<S>)
+            .collect::<HashSet<_>>();
+        self.mempool
+            .retain(|it| !mined.contains(&hash_event::<S>(&it.event)));
+        let mut known = self
+            .mempool()
+            .map(hash_event::<S>)
+            .chain(mined)
+            .collect::<HashSet<_>>();
+        let submitted = Instant::now();
+        let restored = old_chain[fork..]
+            .iter()
+            .flat_map(|block| &block.events)
+            .filter(|event| known.insert(hash_event::<S>(event)))
+            .filter(|event| self.check_queueable(**event).is_ok())
+            .map(|&event| MempoolEntry { submitted, event })
+            .collect::<Vec<_>>();
+        self.mempool.splice(0..0, restored);
+    }
+
+    /// Events waiting to be mined, in submission order.
+    pub fn mempool(&self) -> impl Iterator<Item = &Event<S>> {
+        self.mempool.iter().map(|it| &it.event)
+    }
+
+    /// The mempool transfers from `user` if they can't all be mined, because together
+    /// they spend more than `user`'s balance on the winning chain.
+    ///
+    /// Incoming transfers in the mempool aren't counted, since they might never be mined.
+    /// Returns an empty list if the transfers don't conflict.
+    pub fn conflicting_pending(&self, user: &UserId<S>) -> Vec<&Event<S>> {
+        let pending = self
+            .mempool()
+            .filter(
+                |it| matches!(it, LedgerEvent::Transfer { benefactor, .. } if benefactor == user),
+            )
+            .collect::<Vec<_>>();
+        let spent = pending
+            .iter()
+            .filter_map(|it| it.as_transfer())
+            .try_fold(0u64, |spent, (_, _, amount, _, _)| {
+                spent.checked_add(*amount)
+            });
+        let balance = self
+            .ledger
+            .users()
+            .get(user)
+            .map(|it| *it.balance())
+            .unwrap_or(0);
+        match spent {
+            Some(spent) if spent <= balance => vec![],
+            _ => pending,
+        }
+    }
+
+    /// Drop mempool events that were submitted more than `max_age` ago,
+    /// returning how many were dropped.
+    pub fn expire_mempool(&mut self, max_age: Duration) -> usize {
+        let before = self.mempool.len();
+        self.mempool.retain(|it| it.submitted.elapsed() <= max_age);
+        before - self.mempool.len()
+    }
+
+    /// The ledger that would result from mining the whole mempool on top of the winning chain.
+    ///
+    /// Fails with the index of the first mempool event that can't be applied.
+    pub fn preview_mempool(&self) -> Result<NodeLedger<S>, (usize, AcceptEventError)> {
+        self.mempool()
+            .enumerate()
+            .try_fold(self.ledger.clone(), |ledger, (index, event)| {
+                ledger.with_event(*event).map_err(|error| (index, error))
+            })
+    }
+
+    /// A block of mempool events on top of the winning chain, to be mined with
+    /// [BlockTemplate::mine_until_tip_changes].
+    ///
+    /// Mempool events that can't be applied (in order) are left out.
+    pub fn block_template(&self) -> BlockTemplate<S> {
+        let mut ledger = self.ledger.clone();
+        let events = self
+            .mempool()
+            .filter(|event| match ledger.with_event(**event) {
+                Ok(next) => {
+                    ledger = next;
+                    true
+                }
+                Err(_) => false,
+            })
+            .copied()
+            .collect();
+        BlockTemplate {
+            parent: self.blocks.iter_winning_chain().last().map(|it| it.id),
+            events,
+        }
+    }
+
+    pub fn ingest_block(
+        &mut self,
+        block: WithProofOfWork<NodeBlock<S>>,
+    ) -> Result<(), BlockIngestError> {
+        let _span = validation_span!("ingest_block", events = block.inner.events.len());
+        validate_block_ids(std::slice::from_ref(&block.inner))
+            .map_err(|_| BlockIngestError::IdMismatch)?;
+        if self.reject_empty_blocks && block.inner.parent.is_some() && block.inner.is_empty() {
+            return Err(BlockIngestError::EmptyBlock);
+        }
+
+        // Does this count as easily precomputable? Probably...
+        let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(block.inner.id);
+        {
+            let _span = validation_span!("check_work", target_iterations);
+            crate::check_work(c, re_min, re_max, block.candidate, target_iterations)
+                .map_err(BlockIngestError::DoWorkError)?;
+        }
+
+        let block = block.inner;
+
+        // We can't check the events until we know the state of the parent,
+        // so hold on to the block until the parent arrives.
+        if let Some(parent) = block.parent {
+            if self.blocks.get(&parent).is_none() {
+                if self.refused.contains(&parent) {
+                    self.refuse(block.id);
+                    return Err(BlockIngestError::ParentRefused);
+                }
+                let siblings = self.orphans.get(&parent);
+                let is_held = siblings.is_some_and(|it| it.contains_key(&block.id));
+                if !is_held && self.orphan_count() >= self.max_orphans {
+                    return Err(BlockIngestError::TooManyOrphans);
+                }
+                self.orphans
+                    .entry(parent)
+                    .or_default()
+                    .insert(block.id, block);
+                return Ok(());
+            }
+        }
+
+        let id = block.id;
+        if let Err(error) = self.connect_block(block) {
+            self.refuse(id);
+            return Err(error);
+        }
+        let mut parents = vec![id];
+        while let Some(parent) = parents.pop() {
+            for (id, orphan) in self.orphans.remove(&parent).unwrap_or_default() {
+                match self.connect_block(orphan) {
+                    Ok(()) => parents.push(id),
+                    Err(error) => {
+                        tracing::warn!(%error, "discarding invalid orphan block");
+                        self.refuse(id);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remember `id`, a block which couldn't be connected, and forget the orphans descended
+    /// from it, since they can't be connected either.
+    fn refuse(&mut self, id: BlockId) {
+        let mut parents = vec![id];
+        while let Some(parent) = parents.pop() {
+            if !self.refused.contains(&parent) {
+                if self.refused.len() == REFUSED_CAPACITY {
+                    self.refused.pop_front();
+                }
+                self.refused.push_back(parent);
+            }
+            parents.extend(self.orphans.remove(&parent).unwrap_or_default().into_keys());
+        }
+    }
+
+    /// The [BlockGraph::total_work] of the winning chain.
+    pub fn total_work(&self) -> u64 {
+        self.blocks.total_work()
+    }
+
+    /// How many blocks are waiting for their parent to arrive.
+    pub fn orphan_count(&self) -> usize {
+        self.orphans.values().map(HashMap::len).sum()
+    }
+
+    /// Validate and add a block whose parent (if any) is already in the graph.
+    fn connect_block(&mut self, block: NodeBlock<S>) -> Result<(), BlockIngestError> {
+        // Already accepted, so there's nothing to check, and the ledger needn't move
+        if self.blocks.get(&block.id) == Some(&block) {
+            return Ok(());
+        }
+        let old_chain = self.blocks.winning_chain();
+        let parent_chain = match block.parent {
+            Some(parent) => self
+                .blocks
+                .chain_to(&parent)
+                .expect("parent is in the graph"),
+            None => vec![],
+        };
+        if self.blocks.get(&block.id).is_none() {
+            let depth = old_chain.len() - common_prefix(&old_chain, &parent_chain);
+            if depth > self.max_reorg_depth {
+                return Err(BlockIngestError::ReorgTooDeep { depth });
+            }
+        }
+
+        // Reject invalid events before the block makes it into the graph.
+        // Our ledger is the state at the tip, so move it to the parent (which is free if the
+        // block extends the tip), and extend it in place.
+        let invalid = |(event_index, source)| BlockIngestError::InvalidEvent {
+            event_index,
+            source,
+        };
+        let extended = match block.parent {
+            Some(_) => {
+                self.rewind(&old_chain, &parent_chain);
+                if let Err(error) = self
+                    .ledger
+                    .extend_block(&block.events, self.validation_threads)
+                {
+                    self.rewind(&parent_chain, &old_chain);
+                    return Err(invalid(error));
+                }
+                true
+            }
+            None if self.checkpoint.is_some() => return Err(BlockIngestError::UnexpectedGenesis),
+            None => {
+                apply_block(
+                    self.configure(Ledger::default()),
+                    &block,
+                    self.validation_threads,
+                )
+                .map_err(invalid)?;
+                false
+            }
+        };
+        let events = block.events.len();
+
+        let id = block.id;
+        let (_, _, _, target_iterations) = get_work_params_from_block_id(id);
+        let added = self
+            .blocks
+            .add_block_with_work(block, block_work(target_iterations));
+        if let Ok(AddBlockOk::CanAddNewEventsToLedger) = added {
+            // our ledger is the state at this block, which is the new tip
+            let tip = self.blocks.get(&id).expect("block was just added").clone();
+            self.update_mempool(&[], &[tip]);
+            return Ok(());
+        }
+
+        // Otherwise, move our ledger to wherever the tip is now
+        let current = if extended {
+            self.ledger.undo_events(events);
+            &parent_chain
+        } else {
+            &old_chain
+        };
+        let new_chain = self.blocks.winning_chain();
+        self.rewind(current, &new_chain);
+        self.update_mempool(&old_chain, &new_chain);
+        match added {
+            Ok(AddBlockOk::CanAddNewEventsToLedger) => unreachable!("handled above"),
+            Ok(AddBlockOk::MustRebuildCache) => self.record_reorg(&old_chain, &new_chain),
+            Ok(AddBlockOk::Noop) => {}
+            // Ids are checked against contents on ingestion, so this means SHA-256 is broken,
+            // but that's no reason to take the node down with it
+            Err(AddBlockError::WouldClobber) => {
+                tracing::error!(block_id = %format_args!("{id:x}"), "hash collision");
+                return Err(BlockIngestError::HashCollision);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record_reorg(&mut self, old_chain: &[NodeBlock<S>], new_chain: &[NodeBlock<S>]) {
+        let (Some(old_tip), Some(new_tip)) = (old_chain.last(), new_chain.last()) else {
+            return;
+        };
+        let rolled_back = old_chain.len() - common_prefix(old_chain, new_chain);
+        if rolled_back == 0 || self.recent_reorgs_capacity == 0 {
+            return;
+        }
+        if self.recent_reorgs.len() == self.recent_reorgs_capacity {
+            self.recent_reorgs.pop_front();
+        }
+        self.recent_reorgs.push_back(ReorgRecord {
+            old_height: old_chain.len() - 1,
+            old_tip: old_tip.id,
+            new_height: new_chain.len() - 1,
+            new_tip: new_tip.id,
+            rolled_back,
+        });
+    }
+
+    /// The ledger after applying all the blocks up to and including `block_id`.
+    ///
+    /// Returns [None] if the block is unknown.
+    fn ledger_at(&self, block_id: &BlockId) -> Option<NodeLedger<S>> {
+        let base = match &self.checkpoint {
+            Some(checkpoint) => checkpoint.ledger.clone(),
+            None => Ledger::default(),
+        };
+        let base = unchecked(base);
+        let ledger = self
+            .blocks
+            .chain_to(block_id)?
+            .into_iter()
+            .fold(base, |ledger, block| {
+                apply_block(ledger, &block, self.validation_threads)
+                    .expect("blocks in the graph have been validated")
+            });
+        Some(self.configure(ledger))
+    }
+
+    /// Move [Self::ledger] from the state at the tip of `from` to the state at the tip of `to`,
+    /// both chains in the graph.
+    ///
+    /// Only the events after the two chains fork are undone, and then the rest of `to` is
+    /// applied, without checking it again.
+    /// If they don't share a root, this falls back to [Self::ledger_at].
+    ///
+    /// # Panics
+    /// - if `to` is empty
+    fn rewind(&mut self, from: &[NodeBlock<S>], to: &[NodeBlock<S>]) {
+        let fork = common_prefix(from, to);
+        if fork == 0 {
+            let tip = to.last().expect("chain isn't empty");
+            self.ledger = self.ledger_at(&tip.id).expect("tip is in the graph");
+            return;
+        }
+        self.ledger
+            .undo_events(from[fork..].iter().map(|it| it.events.len()).sum());
+        if fork == to.len() {
+            return;
+        }
+        let mut ledger = unchecked(std::mem::take(&mut self.ledger));
+        for block in &to[fork..] {
+            ledger
+                .extend_block(&block.events, self.validation_threads)
+                .expect("blocks in the graph have been validated");
+        }
+        self.ledger = self.configure(ledger);
+    }
+}
+
+/// `ledger`, but accepting anything that passed when it was ingested, for replaying blocks
+/// from the graph.
+fn unchecked<S: SignatureScheme>(ledger: NodeLedger<S>) -> NodeLedger<S> {
+    // Blocks in the graph had their signatures checked when they were ingested,
+    // perhaps by a different verifier, see [ValidatorNode::set_verifier]
+    ledger
+        .with_verifier(AlwaysAccept)
+        .with_strict_signatures(false)
+        .with_mint_authorities([])
+}
+
+fn apply_block<S: SignatureScheme>(
+    mut ledger: NodeLedger<S>,
+    block: &NodeBlock<S>,
+    num_threads: NonZeroUsize,
+) -> Result<NodeLedger<S>, (usize, AcceptEventError)> {
+    ledger.extend_block(&block.events, num_threads)?;
+    Ok(ledger)
+}
+
+/// Checks that the benefactor signed the [transfer_message] with `S`, and that mints are
+/// signed [mint_message]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SchemeVerifier<S>(PhantomData<S>);
+
+/// The verifier a [ValidatorNode] uses by default.
+pub type P256TransferVerifier = SchemeVerifier<P256>;
+
+impl<S: SignatureScheme> TransferVerifier<UserId<S>, u64, PublicKey<S>, Signature<S>>
+    for SchemeVerifier<S>
+{
+    fn verify(
+        &self,
+        args: TransferVerifierArgs<&UserId<S>, &u64, &PublicKey<S>, &Signature<S>>,
+    ) -> bool {
+        S::verify(
+            args.benefactor_public_key,
+            &transfer_message(args.benefactor, args.beneficiary, *args.amount, args.nonce),
+            args.benefactor_signature,
+        )
+    }
+
+    fn verify_mint(
+        &self,
+        args: MintVerifierArgs<&UserId<S>, &u64, &PublicKey<S>, &Signature<S>>,
+    ) -> bool {
+        S::verify(
+            args.authority_public_key,
+            &mint_message(args.beneficiary, *args.amount, args.nonce),
+            args.authority_signature,
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("event {event_index} in the ledger is inconsistent with the events before it")]
+    InconsistentLedger {
+        event_index: usize,
+        #[source]
+        source: AcceptEventError,
+    },
+    #[error("the ledger doesn't match the winning chain")]
+    StaleLedger,
+    #[error("{minted} was minted, but accounts hold {held}")]
+    SupplyMismatch { minted: u128, held: u128 },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlockIngestError {
+    #[error("block's work was invalid")]
+    DoWorkError(DoWorkError),
+    #[error("block's id doesn't match its contents")]
+    IdMismatch,
+    #[error("node was started from a checkpoint, so cannot accept a new genesis block")]
+    UnexpectedGenesis,
+    #[error("event {event_index} in block was invalid")]
+    InvalidEvent {
+        event_index: usize,
+        #[source]
+        source: AcceptEventError,
+    },
+    #[error("block forks off {depth} blocks below the tip, which is too deep to reorg")]
+    ReorgTooDeep { depth: usize },
+    #[error("block has no events, and isn't genesis")]
+    EmptyBlock,
+    #[error("a different block with the same id is already in the graph")]
+    HashCollision,
+    #[error("block's parent is unknown, and too many other blocks are waiting for theirs")]
+    TooManyOrphans,
+    #[error("block's parent was refused, so it can't be connected either")]
+    ParentRefused,
+}
+
+impl<S: SignatureScheme> WithProofOfWork<NodeBlock<S>> {
+    /// The [candidate_difficulty] of this block's work parameters.
+    pub fn difficulty(&self) -> f64 {
+        let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(self.inner.id);
+        candidate_difficulty(c, re_min, re_max, target_iterations)
+    }
+}
+
+/// Derive the work parameters from the block id, so that work can't be reused between blocks.
+pub(crate) fn get_work_params_from_block_id(id: BlockId) -> (num::Complex<f64>, f64, f64, u16) {
+    // nudge c by up to ±0.01 around 0.5+0.5i, which we know has solutions
+    let nudge = |byte: u8| (f64::from(byte) / f64::from(u8::MAX) - 0.5) * 0.02;
+    (
+        num::Complex {
+            re: 0.5 + nudge(id[0]),
+            im: 0.5 + nudge(id[1]),
+        },
+        DEFAULT_RE_MIN,
+        DEFAULT_RE_MAX,
+        10,
+    )
+}
+
+/// How much a block contributes to the work of its chain, for choosing the winning chain.
+///
+// This is a crude proxy: candidates need to stay in the set for longer, so they're rarer.
+fn block_work(target_iterations: u16) -> u64 {
+    u64::from(target_iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{demo_network, labelled_blocks, mine, Wallet};
+    use std::{collections::BTreeMap, sync::atomic::Ordering};
+
+    /// Mine a block on `parent` that loses the tie-break against `rival` (a block at the same
+    /// height), so that the fork only takes over once it's strictly longer.
+    fn mine_losing_to(
+        parent: BlockId,
+        rival: BlockId,
+        wallet: &Wallet,
+    ) -> WithProofOfWork<NodeBlock> {
+        let events = (0..)
+            .map(|amount| vec![wallet.mint(amount)])
+            .find(|events| hash_block(Some(parent), events) > rival)
+            .unwrap();
+        mine(Some(parent), events)
+    }
+
+    fn balance(node: &ValidatorNode, wallet: &Wallet) -> u64 {
+        *node.ledger().users()[&wallet.id()].balance()
+    }
+
+    #[test]
+    fn load_trusted_checkpoint_then_validate() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        );
+        let block1 = mine(Some(genesis.inner.id), vec![alice.transfer(&bob, 30)]);
+        let mut node = ValidatorNode::new();
+        node.ingest_block(genesis).unwrap();
+        node.ingest_block(block1.clone()).unwrap();
+        let checkpoint = node.checkpoint().unwrap();
+        assert_eq!(checkpoint.block_id, block1.inner.id);
+
+        let block2 = mine(Some(block1.inner.id), vec![bob.transfer(&alice, 10)]);
+        let block3 = mine(Some(block2.inner.id), vec![alice.transfer(&bob, 5)]);
+        let mut trusted =
+            ValidatorNode::load_trusted(checkpoint, [block2, block3.clone()]).unwrap();
+        assert_eq!(balance(&trusted, &alice), 75);
+        assert_eq!(balance(&trusted, &bob), 25);
+
+        // blocks above the checkpoint are still validated
+        let overdraw = mine(Some(block3.inner.id), vec![bob.transfer(&alice, 1000)]);
+        assert!(matches!(
+            trusted.ingest_block(overdraw),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 0,
+                source: AcceptEventError::WouldOverdraw
+            })
+        ));
+        assert!(matches!(
+            trusted.ingest_block(mine(None, vec![])),
+            Err(BlockIngestError::UnexpectedGenesis)
+        ));
+        assert_eq!(balance(&trusted, &bob), 25);
+    }
+
+    #[test]
+    fn recent_reorgs_are_bounded() {
+        let alice = Wallet::new();
+        let mut node = ValidatorNode::new().with_recent_reorgs_capacity(2);
+        let genesis = mine(None, vec![alice.new_user()]);
+        let mut tip = mine(Some(genesis.inner.id), vec![]);
+        node.ingest_block(genesis.clone()).unwrap();
+        node.ingest_block(tip.clone()).unwrap();
+
+        // repeatedly fork off the tip's parent, and overtake
+        let mut expected = vec![];
+        for _ in 0..3 {
+            let old_tip = tip.inner.id;
+            let old_height = node.blocks.winning_chain().len() - 1;
+            let fork = mine_losing_to(tip.inner.parent.unwrap(), old_tip, &alice);
+            node.ingest_block(fork.clone()).unwrap();
+            assert_eq!(node.blocks.winning_chain().last().unwrap().id, old_tip);
+            tip = mine(Some(fork.inner.id), vec![]);
+            node.ingest_block(tip.clone()).unwrap();
+            expected.push(ReorgRecord {
+                old_height,
+                old_tip,
+                new_height: old_height + 1,
+                new_tip: tip.inner.id,
+                rolled_back: 1,
+            });
+        }
+        assert_eq!(
+            node.recent_reorgs().iter().collect::<Vec<_>>(),
+            [&expected[1], &expected[2]]
+        );
+    }
+
+    #[test]
+    fn deep_fork_is_refused() {
+        let alice = Wallet::new();
+        let mut node = ValidatorNode::new().with_max_reorg_depth(2);
+        let genesis = mine(None, vec![alice.new_user()]);
+        let mut chain = vec![genesis.clone()];
+        for _ in 0..4 {
+            chain.push(mine(Some(chain.last().unwrap().inner.id), vec![]));
+        }
+        for block in chain.iter().cloned() {
+            node.ingest_block(block).unwrap();
+        }
+        let tip = node.blocks.winning_chain().last().unwrap().id;
+
+        // a longer fork from genesis would be 4 blocks deep
+        let mut fork = vec![mine(Some(genesis.inner.id), vec![alice.mint(1)])];
+        for _ in 0..5 {
+            fork.push(mine(Some(fork.last().unwrap().inner.id), vec![]));
+        }
+        assert!(matches!(
+            node.ingest_block(fork[0].clone()),
+            Err(BlockIngestError::ReorgTooDeep { depth: 4 })
+        ));
+        // so its descendants can't be connected either, and aren't held on to
+        for block in fork[1..].iter().cloned() {
+            assert!(matches!(
+                node.ingest_block(block),
+                Err(BlockIngestError::ParentRefused)
+            ));
+        }
+        assert_eq!(node.orphan_count(), 0);
+        assert_eq!(node.blocks.winning_chain().last().unwrap().id, tip);
+
+        // but a shallow one is fine
+        node.ingest_block(mine(Some(chain[2].inner.id), vec![alice.mint(1)]))
+            .unwrap();
+        // as is re-ingesting an old block, which isn't checked again
+        let folded = crate::ledger::EVENTS_FOLDED.get();
+        node.ingest_block(genesis).unwrap();
+        assert_eq!(crate::ledger::EVENTS_FOLDED.get(), folded);
+        assert_eq!(node.orphan_count(), 0);
+    }
+
+    #[test]
+    fn fast_path_ledger_matches_replay() {
+        let events = |ledger: &NodeLedger| {
+            ledger.fold_events(vec![], |mut events, _, event| {
+                events.push(*event);
+                events
+            })
+        };
+        let (mut node, wallets) = demo_network(3);
+        for (from, to) in wallets.iter().zip(wallets.iter().cycle().skip(1)) {
+            let tip = node.blocks.winning_chain().last().unwrap().id;
+            let block = mine(Some(tip), vec![from.transfer(to, 7), from.mint(1)]);
+            let folded = crate::ledger::EVENTS_FOLDED.get();
+            node.ingest_block(block).unwrap();
+            // just this block's events
+            assert_eq!(crate::ledger::EVENTS_FOLDED.get() - folded, 2);
+            let replayed = node
+                .ledger_at(&node.blocks.winning_chain().last().unwrap().id)
+                .unwrap();
+            assert_eq!(events(node.ledger()), events(&replayed));
+            assert_eq!(node.ledger().users(), replayed.users());
+        }
+        // a bad block leaves the ledger alone, even if some of its events were fine
+        let users = node.ledger().users();
+        let tip = node.blocks.winning_chain().last().unwrap().id;
+        let overdraw = mine(
+            Some(tip),
+            vec![wallets[0].mint(1), wallets[0].transfer(&wallets[1], 1000)],
+        );
+        assert!(matches!(
+            node.ingest_block(overdraw),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 1,
+                source: AcceptEventError::WouldOverdraw
+            })
+        ));
+        assert_eq!(node.ledger().users(), users);
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn genesis_signatures_are_checked() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let LedgerEvent::Transfer {
+            benefactor_signature,
+            ..
+        } = bob.transfer_with_nonce(&alice, 30, 0)
+        else {
+            unreachable!()
+        };
+        let forged = LedgerEvent::Transfer {
+            benefactor: alice.id(),
+            beneficiary: bob.id(),
+            amount: 30,
+            nonce: 0,
+            benefactor_signature,
+        };
+        let mut node = ValidatorNode::new();
+        assert!(matches!(
+            node.ingest_block(mine(
+                None,
+                vec![alice.new_user(), bob.new_user(), alice.mint(100), forged],
+            )),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 3,
+                source: AcceptEventError::InvalidSignature
+            })
+        ));
+        assert_eq!(node.blocks.block_count(), 0);
+        node.ingest_block(mine(
+            None,
+            vec![
+                alice.new_user(),
+                bob.new_user(),
+                alice.mint(100),
+                alice.transfer(&bob, 30),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(balance(&node, &bob), 30);
+    }
+
+    #[test]
+    fn tampered_block_id_is_rejected() {
+        let alice = Wallet::new();
+        let genesis = mine(None, vec![alice.new_user()]);
+        let block1 = mine(Some(genesis.inner.id), vec![alice.mint(10)]);
+        let block2 = mine(Some(block1.inner.id), vec![]);
+        let mut chain = vec![genesis.inner.clone(), block1.inner.clone(), block2.inner];
+        assert_eq!(validate_block_ids(&chain), Ok(()));
+
+        chain[1].events = vec![alice.mint(1_000_000)];
+        assert_eq!(validate_block_ids(&chain), Err(1));
+
+        let mut node = ValidatorNode::new();
+        node.ingest_block(genesis).unwrap();
+        let mut tampered = block1;
+        tampered.inner.events = vec![alice.mint(1_000_000)];
+        assert!(matches!(
+            node.ingest_block(tampered),
+            Err(BlockIngestError::IdMismatch)
+        ));
+    }
+
+    #[test]
+    fn preview_mempool() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new();
+        node.ingest_block(mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        ))
+        .unwrap();
+        node.submit_event(alice.transfer(&bob, 30)).unwrap();
+        node.submit_event(bob.transfer(&alice, 5)).unwrap(); // can't afford it yet
+
+        let preview = node.preview_mempool().unwrap();
+        assert_eq!(*preview.users()[&alice.id()].balance(), 75);
+        assert_eq!(*preview.users()[&bob.id()].balance(), 25);
+        assert_eq!(balance(&node, &alice), 100);
+
+        node.submit_event(bob.transfer(&alice, 1000)).unwrap();
+        assert!(matches!(
+            node.preview_mempool(),
+            Err((2, AcceptEventError::WouldOverdraw))
+        ));
+    }
+
+    #[test]
+    fn ingest_tip_first() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut chain = vec![mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        )];
+        for amount in 1..5 {
+            let parent = chain.last().unwrap().inner.id;
+            chain.push(mine(Some(parent), vec![alice.transfer(&bob, amount)]));
+        }
+
+        let mut forward = ValidatorNode::new();
+        for block in chain.iter().cloned() {
+            forward.ingest_block(block).unwrap();
+        }
+        let mut reversed = ValidatorNode::new();
+        for block in chain.iter().rev().cloned() {
+            reversed.ingest_block(block).unwrap();
+        }
+        assert_eq!(reversed.orphan_count(), 0);
+        assert_eq!(
+            reversed.blocks.winning_chain(),
+            forward.blocks.winning_chain()
+        );
+        assert_eq!(balance(&reversed, &alice), 90);
+        assert_eq!(balance(&reversed, &bob), 10);
+        assert_eq!(reversed.ledger().users(), forward.ledger().users());
+    }
+
+    #[test]
+    fn orphans_are_bounded() {
+        let alice = Wallet::new();
+        let genesis = mine(None, vec![alice.new_user()]);
+        let mut chain = vec![genesis.clone()];
+        for amount in 0..3 {
+            chain.push(mine(
+                Some(chain.last().unwrap().inner.id),
+                vec![alice.mint(amount)],
+            ));
+        }
+        let mut node = ValidatorNode::new().with_max_orphans(2);
+        node.ingest_block(genesis.clone()).unwrap();
+
+        // the same orphan is only held once
+        for _ in 0..1000 {
+            node.ingest_block(chain[2].clone()).unwrap();
+        }
+        assert_eq!(node.orphan_count(), 1);
+        node.ingest_block(chain[3].clone()).unwrap();
+        let stray = mine(Some(BlockId::default()), vec![alice.mint(1)]);
+        assert!(matches!(
+            node.ingest_block(stray),
+            Err(BlockIngestError::TooManyOrphans)
+        ));
+        node.ingest_block(chain[3].clone()).unwrap();
+        assert_eq!(node.orphan_count(), 2);
+        node.ingest_block(chain[1].clone()).unwrap();
+        assert_eq!(node.orphan_count(), 0);
+        assert_eq!(node.blocks.winning_chain().len(), 4);
+
+        // orphans of a block that's refused are forgotten
+        let mut node = ValidatorNode::new().with_max_reorg_depth(0);
+        node.ingest_block(genesis.clone()).unwrap();
+        node.ingest_block(mine(Some(genesis.inner.id), vec![]))
+            .unwrap();
+        for block in chain[2..].iter().cloned() {
+            node.ingest_block(block).unwrap();
+        }
+        assert_eq!(node.orphan_count(), 2);
+        assert!(matches!(
+            node.ingest_block(chain[1].clone()),
+            Err(BlockIngestError::ReorgTooDeep { depth: 1 })
+        ));
+        assert_eq!(node.orphan_count(), 0);
+    }
+
+    #[test]
+    fn global_event() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        );
+        let empty = mine(Some(genesis.inner.id), vec![]);
+        let (first, second) = (alice.transfer(&bob, 1), alice.transfer(&bob, 2));
+        let transfers = mine(Some(empty.inner.id), vec![first, second]);
+        let mut node = ValidatorNode::new();
+        for block in [genesis.clone(), empty.clone(), transfers.clone()] {
+            node.ingest_block(block).unwrap();
+        }
+        let event =
+            |node: &ValidatorNode, index| node.global_event(index).map(|(id, it)| (id, *it));
+        assert_eq!(event(&node, 0), Some((genesis.inner.id, alice.new_user())));
+        assert_eq!(event(&node, 2), Some((genesis.inner.id, alice.mint(100))));
+        assert_eq!(event(&node, 3), Some((transfers.inner.id, first)));
+        assert_eq!(event(&node, 4), Some((transfers.inner.id, second)));
+        assert_eq!(event(&node, 5), None);
+
+        // indices follow the winning chain
+        let fork = mine(Some(genesis.inner.id), vec![bob.mint(5)]);
+        let mut tip = fork.inner.id;
+        node.ingest_block(fork.clone()).unwrap();
+        for _ in 0..2 {
+            let block = mine(Some(tip), vec![]);
+            tip = block.inner.id;
+            node.ingest_block(block).unwrap();
+        }
+        assert_eq!(event(&node, 3), Some((fork.inner.id, bob.mint(5))));
+        assert_eq!(event(&node, 4), None);
+    }
+
+    #[test]
+    fn state_root_is_independent_of_ingestion_order() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        );
+        let mut blocks = vec![genesis.clone()];
+        for amount in 1..4 {
+            let parent = blocks.last().unwrap().inner.id;
+            blocks.push(mine(Some(parent), vec![alice.transfer(&bob, amount)]));
+        }
+        // a losing fork
+        blocks.push(mine(
+            Some(genesis.inner.id),
+            vec![alice.transfer_with_nonce(&bob, 50, 0)],
+        ));
+
+        let mut forward = ValidatorNode::new();
+        for block in blocks.iter().cloned() {
+            forward.ingest_block(block).unwrap();
+        }
+        let mut reversed = ValidatorNode::new();
+        for block in blocks.iter().rev().cloned() {
+            reversed.ingest_block(block).unwrap();
+        }
+        assert_eq!(forward.state_root(), reversed.state_root());
+
+        let mut behind = ValidatorNode::new();
+        for block in blocks[..3].iter().cloned() {
+            behind.ingest_block(block).unwrap();
+        }
+        assert_ne!(forward.state_root(), behind.state_root());
+
+        // the same balances as just after genesis, but different nonces
+        let mut round_trip = ValidatorNode::new();
+        let there = mine(
+            Some(genesis.inner.id),
+            vec![alice.transfer_with_nonce(&bob, 5, 0)],
+        );
+        let back = mine(Some(there.inner.id), vec![bob.transfer(&alice, 5)]);
+        for block in [genesis.clone(), there, back] {
+            round_trip.ingest_block(block).unwrap();
+        }
+        let mut untouched = ValidatorNode::new();
+        untouched.ingest_block(genesis).unwrap();
+        assert_eq!(
+            (balance(&round_trip, &alice), balance(&round_trip, &bob)),
+            (balance(&untouched, &alice), balance(&untouched, &bob)),
+        );
+        assert_ne!(round_trip.state_root(), untouched.state_root());
+    }
+
+    #[test]
+    fn active_blocks_of() {
+        let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![
+                alice.new_user(),
+                bob.new_user(),
+                carol.new_user(),
+                alice.mint(100),
+            ],
+        );
+        let second = mine(Some(genesis.inner.id), vec![alice.transfer(&bob, 10)]);
+        let third = mine(Some(second.inner.id), vec![bob.transfer(&carol, 5)]);
+        let mut node = ValidatorNode::new();
+        for block in [genesis, second, third] {
+            node.ingest_block(block).unwrap();
+        }
+        assert_eq!(node.active_blocks_of(&alice.id()), 2);
+        assert_eq!(node.active_blocks_of(&bob.id()), 3);
+        assert_eq!(node.active_blocks_of(&carol.id()), 2);
+        assert_eq!(node.active_blocks_of(&Wallet::new().id()), 0);
+    }
+
+    #[test]
+    fn adopt_chain() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut chain = vec![mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        )];
+        for amount in 1..4 {
+            let parent = chain.last().unwrap().inner.id;
+            chain.push(mine(Some(parent), vec![alice.transfer(&bob, amount)]));
+        }
+
+        let (mut node, _) = demo_network(2);
+        let before = node.blocks.winning_chain();
+        let users = node.ledger().users();
+
+        let mut tampered = chain.clone();
+        tampered[2].inner.events = vec![alice.transfer(&bob, 50)];
+        assert!(matches!(
+            node.adopt_chain(tampered),
+            Err(ChainVerifyError::IdMismatch { index: 2 })
+        ));
+        assert!(matches!(
+            node.adopt_chain(chain[1..].to_vec()),
+            Err(ChainVerifyError::BadParentLink { index: 0 })
+        ));
+        assert_eq!(node.blocks.winning_chain(), before);
+        assert_eq!(node.ledger().users(), users);
+        assert!(node.recent_reorgs().is_empty());
+
+        node.adopt_chain(chain.clone()).unwrap();
+        assert_eq!(
+            node.blocks.winning_chain(),
+            chain.iter().map(|it| it.inner.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(node.blocks.block_count(), chain.len());
+        assert_eq!(balance(&node, &alice), 94);
+        assert_eq!(balance(&node, &bob), 6);
+        assert_eq!(node.recent_reorgs().len(), 1);
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn strict_signatures() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new().with_strict_signatures(true);
+        node.set_verifier(AlwaysAccept);
+        let genesis = mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        );
+        let transfer = mine(Some(genesis.inner.id), vec![alice.transfer(&bob, 1)]);
+        // no transfers, so nothing to verify
+        node.ingest_block(genesis).unwrap();
+        assert!(matches!(
+            node.ingest_block(transfer.clone()),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 0,
+                source: AcceptEventError::VerifierNotConfigured,
+            })
+        ));
+        assert!(matches!(
+            node.submit_event(alice.transfer(&bob, 2)),
+            Err(AcceptEventError::VerifierNotConfigured)
+        ));
+
+        node.set_verifier(P256TransferVerifier::default());
+        node.ingest_block(transfer).unwrap();
+        assert_eq!(balance(&node, &bob), 1);
+        node.submit_event(alice.transfer(&bob, 2)).unwrap();
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn set_verifier_only_affects_later_blocks() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        // alice's signature, but not for this amount
+        let forged = |amount, nonce| {
+            let LedgerEvent::Transfer {
+                benefactor_signature,
+                ..
+            } = alice.transfer_with_nonce(&bob, 1, nonce)
+            else {
+                unreachable!()
+            };
+            LedgerEvent::Transfer {
+                benefactor: alice.id(),
+                beneficiary: bob.id(),
+                amount,
+                nonce,
+                benefactor_signature,
+            }
+        };
+        let mut node = ValidatorNode::new();
+        node.set_verifier(AlwaysAccept);
+        let genesis = mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        );
+        let unchecked = mine(Some(genesis.inner.id), vec![forged(2, 0)]);
+        node.ingest_block(genesis).unwrap();
+        node.ingest_block(unchecked.clone()).unwrap();
+
+        node.set_verifier(P256TransferVerifier::default());
+        assert!(matches!(
+            node.ingest_block(mine(Some(unchecked.inner.id), vec![forged(3, 1)])),
+            Err(BlockIngestError::InvalidEvent {
+                source: AcceptEventError::InvalidSignature,
+                ..
+            })
+        ));
+        assert!(matches!(
+            node.submit_event(forged(3, 1)),
+            Err(AcceptEventError::InvalidSignature)
+        ));
+        node.ingest_block(mine(
+            Some(unchecked.inner.id),
+            vec![alice.transfer_with_nonce(&bob, 4, 1)],
+        ))
+        .unwrap();
+        assert_eq!(balance(&node, &bob), 6);
+        // replaying the history doesn't recheck the earlier block
+        node.audit().unwrap();
+        // and neither does receiving it again
+        node.ingest_block(unchecked).unwrap();
+        assert_eq!(balance(&node, &bob), 6);
+    }
+
+    #[test]
+    fn transfers_cant_be_replayed() {
+        let (mut node, wallets) = demo_network(2);
+        let chain = node.blocks.winning_chain();
+        let sent = chain[1].events[0];
+        let tip = chain.last().unwrap().id;
+        assert!(matches!(
+            node.ingest_block(mine(Some(tip), vec![sent])),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 0,
+                source: AcceptEventError::WrongNonce {
+                    expected: 1,
+                    found: 0
+                }
+            })
+        ));
+        assert!(matches!(
+            node.submit_event(sent),
+            Err(AcceptEventError::WrongNonce { .. })
+        ));
+        // but a transfer that's just early is queued
+        node.submit_event(wallets[0].transfer_with_nonce(&wallets[1], 1, 2))
+            .unwrap();
+        assert_eq!(node.mempool().count(), 1);
+        assert!(node.block_template().events.is_empty());
+        node.submit_event(wallets[0].transfer_with_nonce(&wallets[1], 1, 1))
+            .unwrap();
+        assert_eq!(node.block_template().events.len(), 1);
+    }
+
+    #[test]
+    fn submit_event_rejects_the_impossible() {
+        let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new();
+        node.ingest_block(mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        ))
+        .unwrap();
+        assert!(matches!(
+            node.submit_event(alice.transfer(&carol, 1)),
+            Err(AcceptEventError::NoSuchBeneficiary)
+        ));
+        assert!(matches!(
+            node.submit_event(carol.transfer(&alice, 1)),
+            Err(AcceptEventError::NoSuchBenefactor)
+        ));
+        assert!(matches!(
+            node.submit_event(alice.new_user()),
+            Err(AcceptEventError::UserIdTaken)
+        ));
+        let LedgerEvent::Transfer {
+            benefactor_signature,
+            ..
+        } = alice.transfer_with_nonce(&bob, 1, 0)
+        else {
+            unreachable!()
+        };
+        assert!(matches!(
+            node.submit_event(LedgerEvent::Transfer {
+                benefactor: alice.id(),
+                beneficiary: bob.id(),
+                amount: 2,
+                nonce: 0,
+                benefactor_signature
+            }),
+            Err(AcceptEventError::InvalidSignature)
+        ));
+
+        // registering carol in the mempool doesn't help
+        node.submit_event(carol.new_user()).unwrap();
+        assert!(matches!(
+            node.submit_event(alice.transfer(&carol, 1)),
+            Err(AcceptEventError::NoSuchBeneficiary)
+        ));
+        assert_eq!(node.mempool().count(), 1);
+    }
+
+    #[test]
+    fn conflicting_pending() {
+        let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new();
+        node.ingest_block(mine(
+            None,
+            vec![
+                alice.new_user(),
+                bob.new_user(),
+                carol.new_user(),
+                alice.mint(100),
+            ],
+        ))
+        .unwrap();
+        node.submit_event(alice.transfer(&bob, 60)).unwrap();
+        node.submit_event(bob.transfer(&carol, 10)).unwrap();
+        assert!(node.conflicting_pending(&alice.id()).is_empty());
+
+        node.submit_event(alice.transfer(&carol, 50)).unwrap();
+        assert_eq!(
+            node.conflicting_pending(&alice.id()),
+            [
+                &alice.transfer_with_nonce(&bob, 60, 0),
+                &alice.transfer_with_nonce(&carol, 50, 1)
+            ]
+        );
+        // bob's transfer only conflicts with his (empty) balance
+        assert_eq!(
+            node.conflicting_pending(&bob.id()),
+            [&bob.transfer_with_nonce(&carol, 10, 0)]
+        );
+        assert!(node.conflicting_pending(&carol.id()).is_empty());
+    }
+
+    #[test]
+    fn expire_mempool() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new();
+        node.ingest_block(mine(
+            None,
+            vec![alice.new_user(), bob.new_user(), alice.mint(100)],
+        ))
+        .unwrap();
+        let an_hour_ago = Instant::now() - Duration::from_secs(60 * 60);
+        node.submit_event_at(alice.transfer(&bob, 1), an_hour_ago)
+            .unwrap();
+        node.submit_event_at(alice.transfer(&bob, 2), an_hour_ago)
+            .unwrap();
+        node.submit_event(alice.transfer(&bob, 3)).unwrap();
+
+        assert_eq!(node.expire_mempool(Duration::from_secs(2 * 60 * 60)), 0);
+        assert_eq!(node.expire_mempool(Duration::from_secs(60)), 2);
+        assert_eq!(
+            node.mempool().copied().collect::<Vec<_>>(),
+            [alice.transfer_with_nonce(&bob, 3, 2)]
+        );
+    }
+
+    #[test]
+    fn event_inclusion() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let events = vec![
+            alice.new_user(),
+            bob.new_user(),
+            alice.mint(10),
+            alice.transfer(&bob, 3),
+            bob.transfer(&alice, 1),
+        ];
+        let root = events_merkle_root(&events);
+        for (index, event) in events.iter().enumerate() {
+            let proof = event_inclusion_proof(&events, index).unwrap();
+            assert!(verify_event_inclusion(root, event, index, &proof));
+        }
+        assert_eq!(event_inclusion_proof(&events, events.len()), None);
+
+        let proof = event_inclusion_proof(&events, 1).unwrap();
+        assert!(!verify_event_inclusion(root, &events[1], 0, &proof));
+        assert!(!verify_event_inclusion(root, &events[1], 2, &proof));
+        assert!(!verify_event_inclusion(root, &bob.mint(10), 1, &proof));
+        assert!(!verify_event_inclusion(
+            events_merkle_root(&events[..4]),
+            &events[1],
+            1,
+            &proof
+        ));
+    }
+
+    #[test]
+    fn net_flows_are_balances() {
+        let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![
+                alice.new_user(),
+                bob.new_user(),
+                carol.new_user(),
+                alice.mint(100),
+            ],
+        );
+        let block1 = mine(
+            Some(genesis.inner.id),
+            vec![alice.transfer(&bob, 30), bob.mint(5)],
+        );
+        let block2 = mine(Some(block1.inner.id), vec![bob.transfer(&alice, 10)]);
+        let mut node = ValidatorNode::new();
+        for block in [genesis, block1, block2] {
+            node.ingest_block(block).unwrap();
+        }
+        let flows = node.net_flows();
+        assert_eq!(flows[&alice.id()], 80);
+        assert_eq!(flows[&bob.id()], 25);
+        assert_eq!(flows[&carol.id()], 0);
+        for (user, summary) in node.ledger().users() {
+            assert_eq!(flows[&user], i128::from(*summary.balance()));
+        }
+    }
+
+    #[test]
+    fn verify_chain_errors() {
+        let (alice, bob) = (Wallet::new(), Wallet::new());
+        let genesis = mine(None, vec![alice.new_user(), bob.new_user(), alice.mint(10)]);
+        let block1 = mine(Some(genesis.inner.id), vec![alice.transfer(&bob, 4)]);
+        let block2 = mine(Some(block1.inner.id), vec![]);
+        let chain = vec![genesis.clone(), block1.clone(), block2.clone()];
+        let ledger = verify_chain(&chain).unwrap();
+        assert_eq!(*ledger.users()[&bob.id()].balance(), 4);
+
+        let mut bad_work = chain.clone();
+        bad_work[2].candidate = num::Complex { re: 0.0, im: 0.0 };
+        assert!(matches!(
+            verify_chain(&bad_work),
+            Err(ChainVerifyError::BadWork { index: 2, .. })
+        ));
+
+        let skipped = vec![genesis.clone(), block2.clone()];
+        assert!(matches!(
+            verify_chain(&skipped),
+            Err(ChainVerifyError::BadParentLink { index: 1 })
+        ));
+        assert!(matches!(
+            verify_chain(&chain[1..]),
+            Err(ChainVerifyError::BadParentLink { index: 0 })
+        ));
+
+        let mut tampered = chain.clone();
+        tampered[1].inner.events = vec![alice.transfer(&bob, 5)];
+        assert!(matches!(
+            verify_chain(&tampered),
+            Err(ChainVerifyError::IdMismatch { index: 1 })
+        ));
+
+        let overdraw = mine(
+            Some(block1.inner.id),
+            vec![bob.mint(1), bob.transfer(&alice, 100)],
+        );
+        assert!(matches!(
+            verify_chain(&[genesis.clone(), block1, overdraw]),
+            Err(ChainVerifyError::BadEvent {
+                block_index: 2,
+                event_index: 1,
+                source: AcceptEventError::WouldOverdraw
+            })
+        ));
+
+        assert!(matches!(
+            verify_chain(&[genesis, mine(None, vec![])]),
+            Err(ChainVerifyError::UnexpectedGenesis { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn verify_chain_within_budget() {
+        let genesis = mine(None, vec![]);
+        let block1 = mine(Some(genesis.inner.id), vec![]);
+        let block2 = mine(Some(block1.inner.id), vec![]);
+        let chain = [genesis, block1, block2];
+        // every block has 10 target iterations, so costs at most 11
+        assert!(verify_chain_bounded(&chain, 33).is_ok());
+        assert!(matches!(
+            verify_chain_bounded(&chain, 32),
+            Err(ChainVerifyError::WorkBudgetExceeded { index: 2 })
+        ));
+    }
+
+    #[test]
+    fn demo_network_passes_audit() {
+        let (node, wallets) = demo_network(4);
+        node.audit().unwrap();
+        assert_eq!(node.blocks.winning_chain().len(), 5);
+        let total = wallets.iter().map(|it| balance(&node, it)).sum::<u64>();
+        assert_eq!(total, 400);
+        assert_eq!(balance(&node, &wallets[0]), 100 - 1 + 4);
+    }
+
+    #[cfg(feature = "validation-spans")]
+    #[test]
+    fn validation_spans() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::{layer::Context, prelude::*, registry::Registry, Layer};
+
+        #[derive(Clone, Default)]
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+        impl<S: tracing::Subscriber> Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _: &tracing::span::Id,
+                _: Context<'_, S>,
+            ) {
+                self.0.lock().unwrap().push(attrs.metadata().name());
+            }
+        }
+
+        let names = SpanNames::default();
+        let subscriber = Registry::default().with(names.clone());
+        tracing::subscriber::with_default(subscriber, || demo_network(2));
+        let names = names.0.lock().unwrap();
+        for name in [
+            "ingest_block",
+            "check_work",
+            "verify_signatures",
+            "apply_events",
+        ] {
+            assert!(names.contains(&name), "no {name} span in {names:?}");
+        }
+    }
+
+    #[test]
+    fn reject_empty_blocks() {
+        let alice = Wallet::new();
+        let genesis = mine(None, vec![]);
+        let empty = mine(Some(genesis.inner.id), vec![]);
+        let nonempty = mine(Some(genesis.inner.id), vec![alice.new_user()]);
+
+        let mut node = ValidatorNode::new();
+        node.ingest_block(genesis.clone()).unwrap();
+        node.ingest_block(empty.clone()).unwrap();
+
+        let mut node = ValidatorNode::new().with_reject_empty_blocks(true);
+        node.ingest_block(genesis).unwrap();
+        assert!(matches!(
+            node.ingest_block(empty),
+            Err(BlockIngestError::EmptyBlock)
+        ));
+        node.ingest_block(nonempty.clone()).unwrap();
+        assert_eq!(node.blocks.winning_chain().last(), Some(&nonempty.inner));
+    }
+
+    /// Add `labels` from [labelled_blocks] to a graph in that order, with the given work.
+    fn labelled_graph(
+        blocks: &BTreeMap<char, NodeBlock>,
+        labels: impl IntoIterator<Item = (char, u64)>,
+    ) -> NodeGraph {
+        let mut graph = BlockGraph::default();
+        for (label, work) in labels {
+            graph
+                .add_block_with_work(blocks[&label].clone(), work)
+                .unwrap();
+        }
+        graph
+    }
+
+    fn assert_labelled_winning_chain(
+        graph: &NodeGraph,
+        blocks: &BTreeMap<char, NodeBlock>,
+        chain: &str,
+    ) {
+        let label = |id| blocks.iter().find(|(_, it)| it.id == id).unwrap().0;
+        let actual = graph
+            .winning_chain()
+            .into_iter()
+            .map(|it| label(it.id))
+            .collect::<String>();
+        assert_eq!(chain, actual);
+    }
+
+    #[test]
+    fn fork_choice_with_real_ids() {
+        let wallets = (0..5).map(|_| Wallet::new()).collect::<Vec<_>>();
+        let blocks = labelled_blocks([
+            (None, 'a', vec![wallets[0].new_user()]),
+            (Some('a'), 'b', vec![wallets[1].new_user()]),
+            (Some('a'), 'c', vec![wallets[2].new_user()]),
+            (Some('c'), 'd', vec![wallets[3].new_user()]),
+            (None, 'z', vec![wallets[4].new_user()]),
+        ]);
+        validate_block_ids(&blocks.values().cloned().collect::<Vec<_>>()).unwrap();
+        assert_eq!(blocks[&'d'].parent, Some(blocks[&'c'].id));
+
+        // longest chain wins
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, "ab");
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1), ('c', 1), ('d', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, "acd");
+
+        // even if it arrives out of order
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1), ('d', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, "ab");
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1), ('d', 1), ('c', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, "acd");
+
+        // most work wins, across roots too
+        let graph = labelled_graph(&blocks, [('a', 1), ('c', 1), ('d', 1), ('b', 5)]);
+        assert_labelled_winning_chain(&graph, &blocks, "ab");
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1), ('z', 3)]);
+        assert_labelled_winning_chain(&graph, &blocks, "z");
+
+        // then the smallest id, in either order
+        let expected = match blocks[&'b'].id < blocks[&'c'].id {
+            true => "ab",
+            false => "ac",
+        };
+        let graph = labelled_graph(&blocks, [('a', 1), ('b', 1), ('c', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, expected);
+        let graph = labelled_graph(&blocks, [('a', 1), ('c', 1), ('b', 1)]);
+        assert_labelled_winning_chain(&graph, &blocks, expected);
+
+        // tampering is caught
+        let mut tampered = blocks[&'b'].clone();
+        tampered.events = blocks[&'c'].events.clone();
+        assert_eq!(
+            validate_block_ids(&[blocks[&'a'].clone(), tampered]),
+            Err(1)
+        );
+    }
+
+    #[test]
+    fn mine_until_tip_changes() {
+        let (mut node, wallets) = demo_network(2);
+        let tip = node.blocks.winning_chain().last().unwrap().id;
+        // two ways to spend the same nonce
+        node.submit_event(wallets[0].transfer_with_nonce(&wallets[1], 1_000, 1))
+            .unwrap();
+        node.submit_event(wallets[0].transfer_with_nonce(&wallets[1], 10, 1))
+            .unwrap();
+
+        let block = node
+            .block_template()
+            .mine_until_tip_changes(&AtomicBool::new(false))
+            .unwrap();
+        assert_eq!(block.inner.parent, Some(tip));
+        // the overdraw is left out
+        assert_eq!(
+            block.inner.events,
+            vec![wallets[0].transfer_with_nonce(&wallets[1], 10, 1)]
+        );
+        node.ingest_block(block.clone()).unwrap();
+        assert_eq!(node.blocks.winning_chain().last(), Some(&block.inner));
+
+        // mine on another thread, while a rival block arrives
+        let template = node.block_template();
+        let rival = mine(
+            Some(block.inner.id),
+            vec![wallets[1].transfer(&wallets[0], 1)],
+        );
+        let stop = AtomicBool::new(false);
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            let miner = scope.spawn(|| {
+                template.mine_with(|_, _, _, _| {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::yield_now();
+                    }
+                    None
+                })
+            });
+            let tip = node.blocks.winning_chain().last().unwrap().id;
+            node.ingest_block(rival.clone()).unwrap();
+            // there's no point finishing the block now
+            if node.blocks.winning_chain().last().unwrap().id != tip {
+                stop.store(true, Ordering::Relaxed);
+            }
+            assert!(miner.join().unwrap().is_none());
+        });
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(node.blocks.winning_chain().last(), Some(&rival.inner));
+        assert!(node
+            .block_template()
+            .mine_until_tip_changes(&AtomicBool::new(true))
+            .is_none());
+    }
+
+    #[test]
+    fn mined_events_leave_the_mempool() {
+        let (mut node, wallets) = demo_network(2);
+        let tip = node.blocks.winning_chain().last().unwrap().id;
+        let sent = wallets[0].transfer(&wallets[1], 5);
+        node.submit_event(sent).unwrap();
+        let before = balance(&node, &wallets[0]);
+        let mine_template = |node: &mut ValidatorNode| {
+            let template = node.block_template();
+            node.ingest_block(mine(template.parent, template.events))
+                .unwrap();
+        };
+        mine_template(&mut node);
+        mine_template(&mut node);
+        assert_eq!(node.mempool().count(), 0);
+        assert_eq!(balance(&node, &wallets[0]), before - 5);
+        let chain = node.blocks.winning_chain();
+        assert_eq!(chain[chain.len() - 2].events, [sent]);
+        assert!(chain.last().unwrap().is_empty());
+
+        // a longer fork without the transfer puts it back
+        let mut parent = tip;
+        for _ in 0..3 {
+            let block = mine(Some(parent), vec![]);
+            parent = block.inner.id;
+            node.ingest_block(block).unwrap();
+        }
+        assert_eq!(node.blocks.winning_chain().last().unwrap().id, parent);
+        assert_eq!(node.mempool().copied().collect::<Vec<_>>(), [sent]);
+        assert_eq!(balance(&node, &wallets[0]), before);
+
+        // until it's mined again
+        mine_template(&mut node);
+        mine_template(&mut node);
+        assert_eq!(node.mempool().count(), 0);
+        assert_eq!(balance(&node, &wallets[0]), before - 5);
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn mint_authorities() {
+        let (authority, alice) = (Wallet::new(), Wallet::new());
+        let mut node = ValidatorNode::new()
+            .with_mint_authorities([authority.id().0])
+            .with_block_reward(100);
+        // the reward needn't be signed
+        let genesis = mine(None, vec![alice.new_user(), alice.mint(100)]);
+        node.ingest_block(genesis.clone()).unwrap();
+
+        let authorised = mine(
+            Some(genesis.inner.id),
+            vec![authority.authorised_mint(&alice, 5, 0)],
+        );
+        node.ingest_block(authorised.clone()).unwrap();
+        assert_eq!(balance(&node, &alice), 105);
+        assert_eq!(node.ledger().next_mint_nonce(&alice.id()), Some(1));
+
+        for unauthorised in [
+            alice.mint(101),
+            alice.authorised_mint(&alice, 5, 1),
+            // replayed
+            authority.authorised_mint(&alice, 5, 0),
+            authority.authorised_mint(&alice, 5, 2),
+        ] {
+            assert!(matches!(
+                node.ingest_block(mine(Some(authorised.inner.id), vec![unauthorised])),
+                Err(BlockIngestError::InvalidEvent {
+                    event_index: 0,
+                    source: AcceptEventError::UnauthorizedMint
+                })
+            ));
+            assert!(matches!(
+                node.submit_event(unauthorised),
+                Err(AcceptEventError::UnauthorizedMint)
+            ));
+        }
+        // nor can a mint be replayed within a block
+        let twice = authority.authorised_mint(&alice, 5, 1);
+        assert!(matches!(
+            node.ingest_block(mine(Some(authorised.inner.id), vec![twice, twice])),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 1,
+                source: AcceptEventError::UnauthorizedMint
+            })
+        ));
+        assert_eq!(balance(&node, &alice), 105);
+        node.audit().unwrap();
+
+        // adopting a chain is just as strict
+        let forged = mine(
+            Some(authorised.inner.id),
+            vec![authority.authorised_mint(&alice, 5, 0)],
+        );
+        let chain = vec![genesis, authorised, forged];
+        assert!(matches!(
+            node.adopt_chain(chain.clone()),
+            Err(ChainVerifyError::BadEvent {
+                block_index: 2,
+                event_index: 0,
+                source: AcceptEventError::UnauthorizedMint
+            })
+        ));
+        assert_eq!(balance(&node, &alice), 105);
+        ValidatorNode::new().adopt_chain(chain).unwrap();
+    }
+
+    #[test]
+    fn coinbase_is_only_exempt_from_mint_authorities() {
+        let (authority, alice, bob) = (Wallet::new(), Wallet::new(), Wallet::new());
+        let genesis = mine(
+            None,
+            vec![
+                alice.new_user(),
+                bob.new_user(),
+                alice.mint(100),
+                alice.transfer(&bob, 1),
+            ],
+        );
+        let mut node = ValidatorNode::new()
+            .with_mint_authorities([authority.id().0])
+            .with_block_reward(100)
+            .with_strict_signatures(true);
+        node.set_verifier(AlwaysAccept);
+        assert!(matches!(
+            node.ingest_block(genesis.clone()),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 3,
+                source: AcceptEventError::VerifierNotConfigured
+            })
+        ));
+        assert!(matches!(
+            node.adopt_chain(vec![genesis.clone()]),
+            Err(ChainVerifyError::BadEvent {
+                block_index: 0,
+                event_index: 3,
+                source: AcceptEventError::VerifierNotConfigured
+            })
+        ));
+        node.set_verifier(P256TransferVerifier::default());
+        node.adopt_chain(vec![genesis.clone()]).unwrap();
+        assert_eq!(balance(&node, &bob), 1);
+
+        // every block may have one reward, up to the limit
+        let parent = Some(genesis.inner.id);
+        for (rewards, event_index) in [
+            (vec![bob.mint(1), bob.mint(1)], 1),
+            (vec![bob.mint(101)], 0),
+        ] {
+            assert!(matches!(
+                node.ingest_block(mine(parent, rewards)),
+                Err(BlockIngestError::InvalidEvent {
+                    event_index: index,
+                    source: AcceptEventError::UnauthorizedMint
+                }) if index == event_index
+            ));
+        }
+        node.ingest_block(mine(parent, vec![bob.mint(100)]))
+            .unwrap();
+        assert_eq!(balance(&node, &bob), 101);
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn total_work() {
+        let (mut node, wallets) = demo_network(2);
+        assert_eq!(node.total_work(), 3 * block_work(10));
+        let tip = node.blocks.winning_chain().last().unwrap().id;
+        node.ingest_block(mine(Some(tip), vec![wallets[0].transfer(&wallets[1], 1)]))
+            .unwrap();
+        assert_eq!(node.total_work(), 4 * block_work(10));
+    }
+
+    #[test]
+    fn reorgs_adjust_the_ledger_incrementally() {
+        let events = |ledger: &NodeLedger| {
+            ledger.fold_events(vec![], |mut events, _, event| {
+                events.push(*event);
+                events
+            })
+        };
+        let (mut node, wallets) = demo_network(3);
+        let chain = node.blocks.winning_chain();
+        // fork off below the tip, with different transfers, until the fork wins
+        let mut parent = chain[1].id;
+        for (nonce, amount) in [7, 8, 9].into_iter().enumerate() {
+            let transfer = wallets[2].transfer_with_nonce(&wallets[0], amount, nonce as u64);
+            let block = mine(Some(parent), vec![transfer]);
+            parent = block.inner.id;
+            node.ingest_block(block).unwrap();
+            let tip = node.blocks.winning_chain().last().unwrap().id;
+            let rebuilt = node.ledger_at(&tip).unwrap();
+            assert_eq!(events(&node.ledger), events(&rebuilt));
+            assert_eq!(node.ledger.users(), rebuilt.users());
+        }
+        assert_eq!(node.blocks.winning_chain().last().unwrap().id, parent);
+        assert_eq!(node.recent_reorgs().back().unwrap().rolled_back, 2);
+        assert_eq!(balance(&node, &wallets[2]), 100 - 7 - 8 - 9);
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn reorgs_only_fold_the_fork() {
+        let (mut node, wallets) = demo_network(2);
+        for _ in 0..50 {
+            let tip = node.blocks.winning_chain().last().unwrap().id;
+            node.ingest_block(mine(Some(tip), vec![wallets[0].mint(1)]))
+                .unwrap();
+        }
+        let history = node.ledger().fold_events(0, |count, _, _| count + 1);
+        // a side block, then one that wins
+        let mut parent = node.blocks.winning_chain().iter().nth_back(1).unwrap().id;
+        for amount in [1, 2] {
+            let block = mine(Some(parent), vec![wallets[1].transfer(&wallets[0], amount)]);
+            parent = block.inner.id;
+            let folded = crate::ledger::EVENTS_FOLDED.get();
+            node.ingest_block(block).unwrap();
+            let folded = crate::ledger::EVENTS_FOLDED.get() - folded;
+            assert!(folded < 10, "folded {folded} of {history} events");
+        }
+        assert_eq!(node.blocks.winning_chain().last().unwrap().id, parent);
+        let rebuilt = node.ledger_at(&parent).unwrap();
+        assert_eq!(node.ledger().users(), rebuilt.users());
+        node.audit().unwrap();
+    }
+
+    #[test]
+    fn hash_collisions_are_rejected() {
+        let (mut node, wallets) = demo_network(2);
+        let chain = node.blocks.winning_chain();
+        let block = mine(Some(chain[1].id), vec![wallets[0].transfer(&wallets[1], 3)]);
+        // can't be forged through ingest_block, since the id is checked
+        let colliding = Block {
+            events: vec![wallets[1].transfer(&wallets[0], 1)],
+            ..block.inner.clone()
+        };
+        assert!(matches!(
+            node.ingest_block(WithProofOfWork {
+                candidate: block.candidate,
+                inner: colliding.clone(),
+            }),
+            Err(BlockIngestError::IdMismatch)
+        ));
+        // so sneak it into the graph, with too little work to win
+        node.blocks
+            .add_block_with_work(colliding.clone(), 1)
+            .unwrap();
+        let users = node.ledger().users();
+        assert!(matches!(
+            node.ingest_block(block),
+            Err(BlockIngestError::HashCollision)
+        ));
+        assert_eq!(node.blocks.winning_chain(), chain);
+        assert_eq!(node.blocks.get(&colliding.id), Some(&colliding));
+        assert_eq!(node.ledger().users(), users);
+        node.audit().unwrap();
+    }
+
+    /// A toy scheme: a signature is a hash of the message, keyed by the public key.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct Keyed;
+
+    impl SignatureScheme for Keyed {
+        type PublicKey = u64;
+        type Signature = u64;
+        fn verify(public_key: &u64, message: &[u8], signature: &u64) -> bool {
+            *signature == Self::sign(*public_key, message)
+        }
+        fn public_key_bytes(public_key: &u64) -> impl AsRef<[u8]> {
+            public_key.to_be_bytes()
+        }
+        fn signature_bytes(signature: &u64) -> impl AsRef<[u8]> {
+            signature.to_be_bytes()
+        }
+    }
+
+    impl Keyed {
+        fn sign(public_key: u64, message: &[u8]) -> u64 {
+            use std::hash::{BuildHasher as _, BuildHasherDefault, DefaultHasher};
+            BuildHasherDefault::<DefaultHasher>::default().hash_one((public_key, message))
+        }
+    }
+
+    #[test]
+    fn other_signature_schemes() {
+        let mine = |parent, events: Vec<Event<Keyed>>| {
+            let id = hash_block(parent, &events);
+            let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(id);
+            WithProofOfWork {
+                candidate: crate::do_work(c, re_min, re_max, target_iterations),
+                inner: Block { parent, id, events },
+            }
+        };
+        let (alice, bob) = (UserId::<Keyed>(1), UserId::<Keyed>(2));
+        let transfer = |amount, nonce, benefactor_signature| LedgerEvent::Transfer {
+            benefactor: alice,
+            beneficiary: bob,
+            amount,
+            nonce,
+            benefactor_signature,
+        };
+        let genesis = mine(
+            None,
+            vec![
+                LedgerEvent::NewUser {
+                    identifier: alice,
+                    public_key: 1,
+                },
+                LedgerEvent::NewUser {
+                    identifier: bob,
+                    public_key: 2,
+                },
+                LedgerEvent::Mint {
+                    beneficiary: alice,
+                    amount: 100,
+                    authority_signature: None,
+                },
+            ],
+        );
+        let signed = mine(
+            Some(genesis.inner.id),
+            vec![transfer(
+                30,
+                0,
+                Keyed::sign(1, &transfer_message(&alice, &bob, 30, 0)),
+            )],
+        );
+        let forged = mine(
+            Some(signed.inner.id),
+            vec![transfer(
+                30,
+                1,
+                Keyed::sign(2, &transfer_message(&alice, &bob, 30, 1)),
+            )],
+        );
+
+        let mut node = ValidatorNode::<Keyed>::default();
+        node.ingest_block(genesis).unwrap();
+        node.ingest_block(signed).unwrap();
+        assert!(matches!(
+            node.ingest_block(forged),
+            Err(BlockIngestError::InvalidEvent {
+                event_index: 0,
+                source: AcceptEventError::InvalidSignature,
+            })
+        ));
+        assert_eq!(*node.ledger().users()[&bob].balance(), 30);
+        node.audit().unwrap();
+    }
+}
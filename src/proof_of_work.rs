@@ -7,7 +7,16 @@
 // https://www.youtube.com/playlist?list=PL9tHLTl03LqG4ajDvqyfCDMKSxmR_plJ3
 // (Though most of this went way over my head)
 
-use rand::prelude::Distribution;
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    ops::RangeInclusive,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use rand::{prelude::Distribution, SeedableRng as _};
+
+use crate::julia::{check_work, iterate_julia, DoWorkError, DEFAULT_RE_MAX, DEFAULT_RE_MIN};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct WithProofOfWork<T> {
@@ -15,6 +24,60 @@ pub struct WithProofOfWork<T> {
     pub inner: T,
 }
 
+/// Like [WithProofOfWork], but for proofs with several candidates, see [do_work_multi].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithProofsOfWork<T> {
+    pub candidates: Vec<num::Complex<f64>>,
+    pub inner: T,
+}
+
+/// A rectangle of the complex plane to draw candidates from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchRegion {
+    pub centre: num::Complex<f64>,
+    /// Half the width of the rectangle, must be positive.
+    pub re_radius: f64,
+    /// Half the height of the rectangle, must be positive.
+    pub im_radius: f64,
+}
+
+impl SearchRegion {
+    /// The region that [do_work] searches.
+    pub fn default_for(re_min: f64, re_max: f64) -> Self {
+        Self {
+            centre: num::Complex {
+                re: (re_min + re_max) / 2.0,
+                im: 0.0,
+            },
+            re_radius: (re_max - re_min) / 2.0,
+            im_radius: 1.0,
+        }
+    }
+
+    /// The rectangle bounded by `re_min..re_max` and `im_min..im_max`, if it isn't empty.
+    fn bounded_by(re_min: f64, re_max: f64, im_min: f64, im_max: f64) -> Option<Self> {
+        match re_min < re_max && im_min < im_max {
+            true => Some(Self {
+                centre: num::Complex {
+                    re: (re_min + re_max) / 2.0,
+                    im: (im_min + im_max) / 2.0,
+                },
+                re_radius: (re_max - re_min) / 2.0,
+                im_radius: (im_max - im_min) / 2.0,
+            }),
+            false => None,
+        }
+    }
+
+    fn widened(self) -> Self {
+        Self {
+            re_radius: self.re_radius * 2.0,
+            im_radius: self.im_radius * 2.0,
+            ..self
+        }
+    }
+}
+
 // is julia set continuous? Can we do gradient traversal?
 // TODO: find the actual set and work outwards by a certain step?
 pub fn do_work(
@@ -25,55 +88,411 @@ pub fn do_work(
     re_max: f64,
     target_iterations: u16,
 ) -> num::Complex<f64> {
-    let mut rng0 = rand::thread_rng();
-    let mut rng1 = rand::thread_rng();
-    let re_distribution = rand::distributions::Uniform::new(re_min, re_max);
-    let im_distribution = rand::distributions::Uniform::new(-1.0, 1.0);
-    for re in re_distribution.sample_iter(&mut rng0) {
-        for im in im_distribution.sample_iter(&mut rng1) {
-            if let Ok(found) = check_work(
-                c,
-                re_min,
-                re_max,
-                num::Complex { re, im },
-                target_iterations,
-            ) {
-                return found;
+    let never = AtomicBool::new(false);
+    do_work_cancellable(
+        c,
+        re_min,
+        re_max,
+        target_iterations,
+        default_chunk_size(target_iterations),
+        &never,
+    )
+    .expect("never cancelled")
+}
+
+/// A `chunk_size` for [do_work_cancellable] and [do_work_parallel] which checks for
+/// cancellation roughly every hundred thousand iterations of the julia map.
+pub fn default_chunk_size(target_iterations: u16) -> NonZeroUsize {
+    NonZeroUsize::new(100_000 / (usize::from(target_iterations) + 1)).unwrap_or(NonZeroUsize::MIN)
+}
+
+/// Like [do_work], but returns [None] once `stop` is set.
+///
+/// `stop` is checked after every `chunk_size` candidates, so a larger `chunk_size` means
+/// less overhead, but a slower response to cancellation.
+pub fn do_work_cancellable(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    chunk_size: NonZeroUsize,
+    stop: &AtomicBool,
+) -> Option<num::Complex<f64>> {
+    let mut candidates = random_candidates(
+        SearchRegion::default_for(re_min, re_max),
+        rand::thread_rng(),
+    );
+    while !stop.load(Ordering::Relaxed) {
+        for candidate in candidates.by_ref().take(chunk_size.get()) {
+            if let Ok(found) = check_work(c, re_min, re_max, candidate, target_iterations) {
+                return Some(found);
             }
         }
     }
-    unreachable!()
+    None
 }
 
-fn iterate_julia(c: num::Complex<f64>, z: num::Complex<f64>) -> num::Complex<f64> {
-    z.powu(2) + c
+/// Uniformly random candidates from `region`.
+fn random_candidates(
+    region: SearchRegion,
+    mut rng: impl rand::Rng,
+) -> impl Iterator<Item = num::Complex<f64>> {
+    let re_distribution = rand::distributions::Uniform::new(
+        region.centre.re - region.re_radius,
+        region.centre.re + region.re_radius,
+    );
+    let im_distribution = rand::distributions::Uniform::new(
+        region.centre.im - region.im_radius,
+        region.centre.im + region.im_radius,
+    );
+    // sample both components for every candidate - a fixed `re` often has no solutions at all
+    std::iter::repeat_with(move || num::Complex {
+        re: re_distribution.sample(&mut rng),
+        im: im_distribution.sample(&mut rng),
+    })
 }
 
-// how can we deal with floating point errors?
-pub fn check_work(
+/// Estimate how many candidates [do_work] tries on average before it finds a solution,
+/// by checking `samples` random candidates.
+///
+/// Returns [f64::INFINITY] if none of them are solutions.
+pub fn expected_attempts(
     c: num::Complex<f64>,
     re_min: f64,
     re_max: f64,
-    candidate: num::Complex<f64>,
     target_iterations: u16,
-) -> Result<num::Complex<f64>, DoWorkError> {
+    samples: NonZeroUsize,
+    rng: impl rand::Rng,
+) -> f64 {
+    let solutions = random_candidates(SearchRegion::default_for(re_min, re_max), rng)
+        .take(samples.get())
+        .filter(|it| check_work(c, re_min, re_max, *it, target_iterations).is_ok())
+        .count();
+    samples.get() as f64 / solutions as f64
+}
+
+/// How hard it is to find a solution for these parameters, as a number of attempts.
+///
+/// This is [expected_attempts] with a fixed seed, so it's the same every time
+/// (for a given version of [rand]).
+pub fn candidate_difficulty(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+) -> f64 {
+    expected_attempts(
+        c,
+        re_min,
+        re_max,
+        target_iterations,
+        NonZeroUsize::new(100_000).expect("nonzero"),
+        rand::rngs::StdRng::seed_from_u64(0),
+    )
+}
+
+/// Estimate the highest `target_iterations` (up to `max_iterations`) that has any solutions,
+/// by checking up to `sample_budget` random candidates from the rectangle bounded by
+/// `re_min..re_max` and `im_min..im_max`.
+///
+/// Useful for picking difficulty parameters that aren't impossible.
+/// This is only an estimate: a larger budget may find solutions for higher iterations.
+/// Each candidate costs up to `max_iterations + 1` iterations of the julia map.
+/// Returns [None] if no sampled candidate leaves the set within `max_iterations`,
+/// or the rectangle is empty.
+pub fn max_solvable_iterations(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    im_min: f64,
+    im_max: f64,
+    max_iterations: u16,
+    sample_budget: u64,
+) -> Option<u16> {
+    let region = SearchRegion::bounded_by(re_min, re_max, im_min, im_max)?;
+    let mut highest = None;
+    for candidate in random_candidates(region, rand::rngs::StdRng::seed_from_u64(0))
+        .take(usize::try_from(sample_budget).unwrap_or(usize::MAX))
+    {
+        highest = highest.max(escape_iteration(
+            c,
+            re_min,
+            re_max,
+            candidate,
+            max_iterations,
+        ));
+        if highest == Some(max_iterations) {
+            break;
+        }
+    }
+    highest
+}
+
+/// Estimate the fraction of random candidates from the rectangle bounded by
+/// `re_min..re_max` and `im_min..im_max` that are solutions, for each of `iteration_range`.
+///
+/// This is the reciprocal of [expected_attempts], with the same `samples` candidates
+/// checked for every `target_iterations`, so it can be plotted to pick parameters.
+/// With no samples, or an empty rectangle, every estimate is `0.0`.
+pub fn difficulty_surface(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    im_min: f64,
+    im_max: f64,
+    iteration_range: RangeInclusive<u16>,
+    samples: u64,
+) -> Vec<(u16, f64)> {
+    let mut solutions = vec![0u64; iteration_range.len()];
+    if let Some(region) = SearchRegion::bounded_by(re_min, re_max, im_min, im_max) {
+        // a candidate is only a solution for the iteration it escapes at
+        for candidate in random_candidates(region, rand::rngs::StdRng::seed_from_u64(0))
+            .take(usize::try_from(samples).unwrap_or(usize::MAX))
+        {
+            if let Some(iteration) =
+                escape_iteration(c, re_min, re_max, candidate, *iteration_range.end())
+            {
+                if let Some(count) = iteration
+                    .checked_sub(*iteration_range.start())
+                    .and_then(|it| solutions.get_mut(usize::from(it)))
+                {
+                    *count += 1;
+                }
+            }
+        }
+    }
+    iteration_range
+        .zip(solutions)
+        .map(|(target_iterations, solutions)| {
+            let fraction = match samples {
+                0 => 0.0,
+                samples => solutions as f64 / samples as f64,
+            };
+            (target_iterations, fraction)
+        })
+        .collect()
+}
+
+/// The `target_iterations` that `candidate` is a solution for, if any up to `max_iterations`.
+fn escape_iteration(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    candidate: num::Complex<f64>,
+    max_iterations: u16,
+) -> Option<u16> {
     let mut current = candidate;
-    for iteration in 0..=target_iterations {
+    for iteration in 0..=max_iterations {
         current = iterate_julia(c, current);
         if current.re < re_min || current.re > re_max {
-            match iteration == target_iterations {
-                true => return Ok(candidate),
-                false => return Err(DoWorkError::LeftSetTooEarly),
+            return Some(iteration);
+        }
+    }
+    None
+}
+
+/// Like [do_work], but starts by searching `region`, and doubles its width and height after
+/// `patience` failed attempts, in case it's a region with few (or no) solutions.
+///
+/// The region then has four times the area, so gets four times the attempts before
+/// it's widened again, and so on.
+/// It stops growing after [MAX_WIDENINGS] doublings.
+///
+/// Only where candidates are drawn from changes: every candidate is checked against the
+/// given params, so the result is always valid for them.
+pub fn do_work_with_backoff(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    region: SearchRegion,
+    patience: NonZeroUsize,
+) -> num::Complex<f64> {
+    let (mut region, mut patience) = (region, patience.get());
+    for _ in 0..MAX_WIDENINGS {
+        if let Some(found) = random_candidates(region, rand::thread_rng())
+            .take(patience)
+            .find(|it| check_work(c, re_min, re_max, *it, target_iterations).is_ok())
+        {
+            return found;
+        }
+        tracing::debug!(?region, "no solutions found, widening the search");
+        region = region.widened();
+        patience = patience.saturating_mul(4);
+    }
+    random_candidates(region, rand::thread_rng())
+        .find(|it| check_work(c, re_min, re_max, *it, target_iterations).is_ok())
+        .expect("infinite iterator")
+}
+
+/// See [do_work_with_backoff].
+pub const MAX_WIDENINGS: usize = 8;
+
+/// Like [do_work], but searches on `num_threads` threads at once, returning the first solution.
+///
+/// See [do_work_cancellable] for `chunk_size`.
+pub fn do_work_parallel(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    num_threads: NonZeroUsize,
+    chunk_size: NonZeroUsize,
+) -> num::Complex<f64> {
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..num_threads.get() {
+            let (sender, stop) = (sender.clone(), &stop);
+            scope.spawn(move || {
+                if let Some(found) =
+                    do_work_cancellable(c, re_min, re_max, target_iterations, chunk_size, stop)
+                {
+                    stop.store(true, Ordering::Relaxed);
+                    let _ = sender.send(found); // first one wins
+                }
+            });
+        }
+        receiver
+            .recv()
+            .expect("a thread only exits once it's found a solution")
+    })
+}
+
+/// Like [do_work], but searches around `seed` rather than the whole plane.
+///
+/// Candidates are drawn from a square of side `2 * radius` centred on `seed`, which
+/// shrinks every so often, so a `seed` close to a solution (e.g the solution for a
+/// previous block) converges quickly.
+/// Once the square is vanishingly small, it's reset to the full `radius`.
+pub fn do_work_near(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    seed: num::Complex<f64>,
+    radius: f64,
+) -> num::Complex<f64> {
+    search_near(c, re_min, re_max, target_iterations, seed, radius).0
+}
+
+/// Returns the solution, and how many candidates were tried.
+fn search_near(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    seed: num::Complex<f64>,
+    radius: f64,
+) -> (num::Complex<f64>, u64) {
+    const ATTEMPTS_PER_RADIUS: u64 = 64;
+    const MIN_RADIUS_FRACTION: f64 = 1e-6;
+    let mut rng = rand::thread_rng();
+    let offset = rand::distributions::Uniform::new_inclusive(-1.0, 1.0);
+    let (mut current_radius, mut attempt) = (radius, 0);
+    loop {
+        attempt += 1;
+        let candidate = seed
+            + num::Complex {
+                re: offset.sample(&mut rng),
+                im: offset.sample(&mut rng),
+            } * current_radius;
+        if let Ok(found) = check_work(c, re_min, re_max, candidate, target_iterations) {
+            return (found, attempt);
+        }
+        if attempt % ATTEMPTS_PER_RADIUS == 0 {
+            current_radius /= 2.0;
+            if current_radius < radius * MIN_RADIUS_FRACTION {
+                current_radius = radius;
             }
         }
     }
-    Err(DoWorkError::LeftSetTooLateOrNotAtAll)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DoWorkError {
-    LeftSetTooEarly,
-    LeftSetTooLateOrNotAtAll,
+/// Find `count` distinct candidates, which makes a proof `count` times as expensive to forge.
+///
+/// See [check_work_multi].
+pub fn do_work_multi(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    target_iterations: u16,
+    count: usize,
+) -> Vec<num::Complex<f64>> {
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::with_capacity(count);
+    while candidates.len() < count {
+        let found = do_work(c, re_min, re_max, target_iterations);
+        if seen.insert(bits(found)) {
+            candidates.push(found)
+        }
+    }
+    candidates
+}
+
+/// Check a proof from [do_work_multi]: there must be exactly `count` candidates,
+/// with no duplicates, each of which passes [check_work].
+pub fn check_work_multi(
+    c: num::Complex<f64>,
+    re_min: f64,
+    re_max: f64,
+    candidates: &[num::Complex<f64>],
+    target_iterations: u16,
+    count: usize,
+) -> Result<(), MultiWorkError> {
+    if candidates.len() != count {
+        return Err(MultiWorkError::WrongCount {
+            expected: count,
+            actual: candidates.len(),
+        });
+    }
+    let mut seen = HashSet::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        if !seen.insert(bits(*candidate)) {
+            return Err(MultiWorkError::Duplicate { index });
+        }
+        check_work(c, re_min, re_max, *candidate, target_iterations)
+            .map_err(|source| MultiWorkError::BadCandidate { index, source })?;
+    }
+    Ok(())
+}
+
+/// Candidates are only the same if they're bitwise identical.
+fn bits(candidate: num::Complex<f64>) -> (u64, u64) {
+    (candidate.re.to_bits(), candidate.im.to_bits())
+}
+
+/// The points that `candidate` visits under repeated [iterate_julia], as [check_work] sees them.
+///
+/// Stops at the first point outside of [DEFAULT_RE_MIN]..=[DEFAULT_RE_MAX],
+/// or after `target_iterations + 1` points.
+/// So with the default bounds, a candidate is accepted if its orbit is full length and the
+/// last point is outside the bounds.
+pub fn julia_orbit(
+    c: num::Complex<f64>,
+    candidate: num::Complex<f64>,
+    target_iterations: u16,
+) -> Vec<num::Complex<f64>> {
+    let mut orbit = Vec::with_capacity(usize::from(target_iterations) + 1);
+    let mut current = candidate;
+    for _ in 0..=target_iterations {
+        current = iterate_julia(c, current);
+        orbit.push(current);
+        if current.re < DEFAULT_RE_MIN || current.re > DEFAULT_RE_MAX {
+            break;
+        }
+    }
+    orbit
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum MultiWorkError {
+    #[error("expected {expected} candidates, but got {actual}")]
+    WrongCount { expected: usize, actual: usize },
+    #[error("candidate {index} is a duplicate")]
+    Duplicate { index: usize },
+    #[error("candidate {index} is invalid")]
+    BadCandidate { index: usize, source: DoWorkError },
 }
 
 #[cfg(test)]
@@ -104,4 +523,197 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn cancelled_work_stops() {
+        let stop = AtomicBool::new(true);
+        assert_eq!(
+            do_work_cancellable(
+                num::Complex { re: 0.5, im: 0.5 },
+                0.0,
+                0.5,
+                10,
+                NonZeroUsize::MIN,
+                &stop
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn parallel_work_is_valid() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        for chunk_size in [1, 10, 1000] {
+            let found = do_work_parallel(
+                c,
+                0.0,
+                0.5,
+                10,
+                NonZeroUsize::new(4).unwrap(),
+                NonZeroUsize::new(chunk_size).unwrap(),
+            );
+            assert_eq!(check_work(c, 0.0, 0.5, found, 10), Ok(found));
+        }
+    }
+
+    #[test]
+    fn seeded_work_is_faster() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let seed = num::Complex {
+            re: 0.112,
+            im: 0.644,
+        };
+        let runs = 50;
+        let mut rng = rand::thread_rng();
+        let re_distribution = rand::distributions::Uniform::new(0.0, 0.5);
+        let im_distribution = rand::distributions::Uniform::new(-1.0, 1.0);
+        let (mut blind, mut seeded) = (0, 0);
+        for _ in 0..runs {
+            blind += (1..)
+                .find(|_| {
+                    let candidate = num::Complex {
+                        re: re_distribution.sample(&mut rng),
+                        im: im_distribution.sample(&mut rng),
+                    };
+                    check_work(c, 0.0, 0.5, candidate, 10).is_ok()
+                })
+                .unwrap();
+            let (found, attempts) = search_near(c, 0.0, 0.5, 10, seed, 0.01);
+            assert_eq!(check_work(c, 0.0, 0.5, found, 10), Ok(found));
+            seeded += attempts;
+        }
+        assert!(seeded * 10 < blind, "blind: {blind}, seeded: {seeded}");
+    }
+
+    #[test]
+    fn orbit_agrees_with_check_work() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let escaped = |it: &num::Complex<f64>| it.re < DEFAULT_RE_MIN || it.re > DEFAULT_RE_MAX;
+        let mut rng = rand::thread_rng();
+        let distribution = rand::distributions::Uniform::new(-1.0, 1.0);
+        let known_good = num::Complex {
+            re: 0.11138718573116269,
+            im: 0.6446882805476304,
+        };
+        let candidates = std::iter::once(known_good).chain((0..1000).map(|_| num::Complex {
+            re: distribution.sample(&mut rng),
+            im: distribution.sample(&mut rng),
+        }));
+        for candidate in candidates {
+            let orbit = julia_orbit(c, candidate, 10);
+            let accepted = orbit.len() == 11 && escaped(orbit.last().unwrap());
+            assert!(orbit[..orbit.len() - 1].iter().all(|it| !escaped(it)));
+            assert_eq!(
+                accepted,
+                check_work(c, DEFAULT_RE_MIN, DEFAULT_RE_MAX, candidate, 10).is_ok()
+            );
+        }
+        assert_eq!(julia_orbit(c, known_good, 10).len(), 11);
+    }
+
+    #[test]
+    fn multi_candidate_proof() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let check =
+            |candidates: &[num::Complex<f64>]| check_work_multi(c, 0.0, 0.5, candidates, 10, 3);
+        let proof = WithProofsOfWork {
+            candidates: do_work_multi(c, 0.0, 0.5, 10, 3),
+            inner: (),
+        };
+        assert_eq!(check(&proof.candidates), Ok(()));
+
+        let [first, second, _] = proof.candidates[..] else {
+            panic!("wrong number of candidates")
+        };
+        assert_eq!(
+            check(&[first, second]),
+            Err(MultiWorkError::WrongCount {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            check(&[first, second, first]),
+            Err(MultiWorkError::Duplicate { index: 2 })
+        );
+        assert!(matches!(
+            check(&[first, num::Complex { re: 0.0, im: 0.0 }, second]),
+            Err(MultiWorkError::BadCandidate { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn narrower_acceptance_is_more_difficult() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let easier = candidate_difficulty(c, 0.0, 0.5, 10);
+        let harder = candidate_difficulty(c, 0.0, 0.5, 12);
+        assert!(easier.is_finite());
+        assert!(easier < harder, "easier: {easier}, harder: {harder}");
+        assert_eq!(easier, candidate_difficulty(c, 0.0, 0.5, 10));
+        assert_eq!(candidate_difficulty(c, 0.1, 0.5, 10), f64::INFINITY);
+    }
+
+    #[test]
+    fn max_solvable_iterations() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let highest =
+            super::max_solvable_iterations(c, 0.0, 0.5, -1.0, 1.0, u16::MAX, 100_000).unwrap();
+        assert!(highest >= 10, "{highest}");
+        assert_eq!(
+            super::max_solvable_iterations(c, 0.0, 0.5, -1.0, 1.0, 10, 100_000),
+            Some(10)
+        );
+        assert_eq!(
+            super::max_solvable_iterations(c, 0.5, 0.5, -1.0, 1.0, u16::MAX, 100_000),
+            None
+        );
+        // z -> z^2 never leaves the unit disc
+        let zero = num::Complex { re: 0.0, im: 0.0 };
+        assert_eq!(
+            super::max_solvable_iterations(zero, -0.5, 0.5, -0.5, 0.5, 1000, 100),
+            None
+        );
+    }
+
+    #[test]
+    fn difficulty_surface() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        // beyond this there are only a handful of solutions, so the estimates are noisy
+        let surface = super::difficulty_surface(c, 0.0, 0.5, -1.0, 1.0, 0..=12, 100_000);
+        assert_eq!(
+            surface.iter().map(|(it, _)| *it).collect::<Vec<_>>(),
+            (0..=12).collect::<Vec<_>>()
+        );
+        for window in surface.windows(2) {
+            assert!(window[0].1 >= window[1].1, "{window:?}");
+        }
+        // the same candidates as `candidate_difficulty`
+        let (_, fraction) = surface[10];
+        assert_eq!(
+            (fraction * 100_000.0).round(),
+            (100_000.0 / candidate_difficulty(c, 0.0, 0.5, 10)).round()
+        );
+
+        let shifted = super::difficulty_surface(c, 0.0, 0.5, -1.0, 1.0, 10..=12, 100_000);
+        assert_eq!(shifted[0], surface[10]);
+        assert_eq!(
+            super::difficulty_surface(c, 0.0, 0.5, 1.0, 1.0, 0..=1, 100),
+            [(0, 0.0), (1, 0.0)]
+        );
+    }
+
+    #[test]
+    fn backoff_escapes_a_bad_region() {
+        let c = num::Complex { re: 0.5, im: 0.5 };
+        let bad = SearchRegion {
+            centre: num::Complex { re: 0.4, im: 0.0 },
+            re_radius: 0.05,
+            im_radius: 0.05,
+        };
+        assert!(!random_candidates(bad, rand::thread_rng())
+            .take(100_000)
+            .any(|it| check_work(c, 0.0, 0.5, it, 10).is_ok()));
+        let found = do_work_with_backoff(c, 0.0, 0.5, 10, bad, NonZeroUsize::new(1000).unwrap());
+        assert_eq!(check_work(c, 0.0, 0.5, found, 10), Ok(found));
+    }
 }
@@ -0,0 +1,69 @@
+use std::{fmt, hash::Hash};
+
+use p256::ecdsa::signature::Verifier as _;
+
+/// How users prove that they authorised a [crate::LedgerEvent::Transfer].
+///
+/// [crate::Ledger] is generic over the public key and signature types, and is handed a
+/// verifier, so any scheme can be used with it.
+/// [crate::ValidatorNode] is generic over its scheme, and uses [P256] by default.
+///
+/// Implementors are marker types, like [P256], so the bounds are free to satisfy.
+pub trait SignatureScheme:
+    fmt::Debug
+    + Clone
+    + Copy
+    + Default
+    + PartialEq
+    + Eq
+    + PartialOrd
+    + Ord
+    + Hash
+    + Send
+    + Sync
+    + 'static
+{
+    type PublicKey: fmt::Debug + Copy + Ord + Send + Sync + 'static;
+    type Signature: fmt::Debug + Copy + Eq + Send + Sync + 'static;
+    /// Whether `signature` is `public_key`'s signature of `message`.
+    fn verify(public_key: &Self::PublicKey, message: &[u8], signature: &Self::Signature) -> bool;
+    /// A canonical encoding of `public_key`, for hashing and signing.
+    fn public_key_bytes(public_key: &Self::PublicKey) -> impl AsRef<[u8]>;
+    /// A canonical encoding of `signature`, for hashing.
+    fn signature_bytes(signature: &Self::Signature) -> impl AsRef<[u8]>;
+}
+
+/// ECDSA over the NIST P-256 curve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct P256;
+
+impl SignatureScheme for P256 {
+    type PublicKey = p256::ecdsa::VerifyingKey;
+    type Signature = p256::ecdsa::Signature;
+    fn verify(public_key: &Self::PublicKey, message: &[u8], signature: &Self::Signature) -> bool {
+        public_key.verify(message, signature).is_ok()
+    }
+    /// The compressed SEC1 encoding.
+    fn public_key_bytes(public_key: &Self::PublicKey) -> impl AsRef<[u8]> {
+        public_key.to_encoded_point(true)
+    }
+    fn signature_bytes(signature: &Self::Signature) -> impl AsRef<[u8]> {
+        signature.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer as _, SigningKey};
+
+    #[test]
+    fn p256() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let signature = key.sign(b"hello");
+        assert!(P256::verify(key.verifying_key(), b"hello", &signature));
+        assert!(!P256::verify(key.verifying_key(), b"goodbye", &signature));
+        let other = SigningKey::random(&mut rand::thread_rng());
+        assert!(!P256::verify(other.verifying_key(), b"hello", &signature));
+    }
+}
@@ -0,0 +1,155 @@
+//! Helpers for spinning up realistic nodes in tests and examples.
+//!
+//! Enabled by the `testing` feature.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use p256::ecdsa::{signature::Signer as _, SigningKey};
+
+use crate::{
+    do_work, hash_block, mint_message,
+    node::{get_work_params_from_block_id, BlockId, Event, NodeBlock},
+    transfer_message, Block, LedgerEvent, UserId, ValidatorNode, WithProofOfWork,
+};
+
+/// A user's signing key, which can create events on their behalf.
+pub struct Wallet {
+    key: SigningKey,
+    /// See [Self::transfer].
+    next_nonce: AtomicU64,
+}
+
+impl Wallet {
+    /// A wallet with a fresh random key.
+    pub fn new() -> Self {
+        Self {
+            key: SigningKey::random(&mut rand::thread_rng()),
+            next_nonce: AtomicU64::new(0),
+        }
+    }
+    pub fn id(&self) -> UserId {
+        UserId(*self.key.verifying_key())
+    }
+    pub fn new_user(&self) -> Event {
+        LedgerEvent::NewUser {
+            identifier: self.id(),
+            public_key: *self.key.verifying_key(),
+        }
+    }
+    pub fn mint(&self, amount: u64) -> Event {
+        LedgerEvent::Mint {
+            beneficiary: self.id(),
+            amount,
+            authority_signature: None,
+        }
+    }
+    /// A mint to `to`, signed by this wallet as a minting authority, for `to`'s
+    /// [crate::UserSummary::next_mint_nonce] of `nonce`.
+    pub fn authorised_mint(&self, to: &Wallet, amount: u64, nonce: u64) -> Event {
+        LedgerEvent::Mint {
+            beneficiary: to.id(),
+            amount,
+            authority_signature: Some(self.key.sign(&mint_message(&to.id(), amount, nonce))),
+        }
+    }
+    /// A transfer from this wallet, signed by this wallet.
+    ///
+    /// Each transfer takes the next nonce, as if every transfer before it is accepted in order.
+    /// Use [Self::transfer_with_nonce] otherwise.
+    pub fn transfer(&self, to: &Wallet, amount: u64) -> Event {
+        let nonce = self.next_nonce.fetch_add(1, Ordering::Relaxed);
+        self.transfer_with_nonce(to, amount, nonce)
+    }
+    /// A transfer from this wallet with this nonce, signed by this wallet.
+    ///
+    /// This doesn't affect the nonces [Self::transfer] uses.
+    pub fn transfer_with_nonce(&self, to: &Wallet, amount: u64, nonce: u64) -> Event {
+        LedgerEvent::Transfer {
+            benefactor: self.id(),
+            beneficiary: to.id(),
+            amount,
+            nonce,
+            benefactor_signature: self.key.sign(&transfer_message(
+                &self.id(),
+                &to.id(),
+                amount,
+                nonce,
+            )),
+        }
+    }
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build a block with these events, and do its work.
+pub fn mine(parent: Option<BlockId>, events: Vec<Event>) -> WithProofOfWork<NodeBlock> {
+    let id = hash_block(parent, &events);
+    let (c, re_min, re_max, target_iterations) = get_work_params_from_block_id(id);
+    WithProofOfWork {
+        candidate: do_work(c, re_min, re_max, target_iterations),
+        inner: Block { parent, id, events },
+    }
+}
+
+/// Hash a small graph of real blocks, for writing tests like the `char`-keyed [crate::BlockGraph]
+/// tests.
+///
+/// Each block is `(parent, label, events)`, and refers to its parent by label, so parents must
+/// come before their children.
+/// Siblings need different events, or they'll have the same id.
+///
+/// # Panics
+/// - If a parent hasn't been seen yet.
+pub fn labelled_blocks(
+    blocks: impl IntoIterator<Item = (Option<char>, char, Vec<Event>)>,
+) -> BTreeMap<char, NodeBlock> {
+    let mut labelled = BTreeMap::new();
+    for (parent, label, events) in blocks {
+        let parent = parent.map(|parent| {
+            labelled
+                .get(&parent)
+                .map(|it: &Block<_, _, _, _, _>| it.id)
+                .unwrap_or_else(|| panic!("block {label}'s parent {parent} hasn't been seen"))
+        });
+        let id = hash_block(parent, &events);
+        labelled.insert(label, Block { parent, id, events });
+    }
+    labelled
+}
+
+/// A node that has ingested a short chain:
+/// - a genesis block which registers `num_wallets` wallets, and mints 100 to each
+/// - a block per wallet, where it sends some money to the next wallet
+pub fn demo_network(num_wallets: usize) -> (ValidatorNode, Vec<Wallet>) {
+    let wallets = (0..num_wallets).map(|_| Wallet::new()).collect::<Vec<_>>();
+    let genesis = mine(
+        None,
+        wallets
+            .iter()
+            .map(Wallet::new_user)
+            .chain(wallets.iter().map(|it| it.mint(100)))
+            .collect(),
+    );
+    let mut node = ValidatorNode::new();
+    let mut parent = genesis.inner.id;
+    node.ingest_block(genesis).expect("genesis is valid");
+    if num_wallets > 1 {
+        for (index, (from, to)) in wallets
+            .iter()
+            .zip(wallets.iter().cycle().skip(1))
+            .enumerate()
+        {
+            let block = mine(Some(parent), vec![from.transfer(to, index as u64 + 1)]);
+            parent = block.inner.id;
+            node.ingest_block(block).expect("transfers are valid");
+        }
+    }
+    (node, wallets)
+}